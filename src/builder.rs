@@ -0,0 +1,139 @@
+//! A builder that configures a [`MovingMedian`] up front.
+
+use crate::{EvenStrategy, MovingMedian, NanPolicy, Sample};
+
+/// A builder for a [`MovingMedian`] whose configuration is chosen before the first value is added.
+///
+/// Every option starts at the default of [`MovingMedian::new`], so a builder without options
+/// builds the same filter. The builder is a plain value that holds no buffer of its own.
+///
+/// ```
+/// use moving_median::{EvenStrategy, MovingMedian};
+///
+/// let mut filter = MovingMedian::<f32, 8>::builder()
+///     .empty_default(20.0)
+///     .ignore_nan(false)
+///     .even_strategy(EvenStrategy::TakeLower)
+///     .window(4)
+///     .build();
+///
+/// assert_eq!(filter.median(), 20.0);
+/// filter.add_values(&[1.0, 2.0, 3.0, 4.0, 5.0]);
+/// assert_eq!(filter.median(), 3.0);
+/// filter.add_value(f32::NAN);
+/// assert!(filter.median().is_nan());
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct MovingMedianBuilder<T, const N: usize> {
+    empty_default: Option<T>,
+    nan_policy: NanPolicy,
+    even_strategy: EvenStrategy,
+    window: usize,
+}
+
+impl<T: Sample, const N: usize> MovingMedianBuilder<T, N> {
+    /// Create a builder with the default configuration, see also [`MovingMedian::builder`].
+    pub fn new() -> Self {
+        Self {
+            empty_default: None,
+            nan_policy: NanPolicy::Ignore,
+            even_strategy: EvenStrategy::AverageMiddle,
+            window: N,
+        }
+    }
+
+    /// The median of the filter while it holds no values, instead of zero,
+    /// like [`clear_with_default`](MovingMedian::clear_with_default).
+    pub fn empty_default(mut self, value: T) -> Self {
+        self.empty_default = Some(value);
+        self
+    }
+
+    /// Leave NaN values out of the median if `true`, the default, or report NaN as the median if
+    /// `false`, see [`NanPolicy`].
+    pub fn ignore_nan(self, ignore: bool) -> Self {
+        self.nan_policy(if ignore {
+            NanPolicy::Ignore
+        } else {
+            NanPolicy::Propagate
+        })
+    }
+
+    /// The policy used for NaN values, see [`set_nan_policy`](MovingMedian::set_nan_policy).
+    pub fn nan_policy(mut self, policy: NanPolicy) -> Self {
+        self.nan_policy = policy;
+        self
+    }
+
+    /// The strategy for the median of an even number of values,
+    /// see [`set_even_strategy`](MovingMedian::set_even_strategy).
+    pub fn even_strategy(mut self, strategy: EvenStrategy) -> Self {
+        self.even_strategy = strategy;
+        self
+    }
+
+    /// A window of the most recent `len` values, clamped to 1 to N,
+    /// see [`set_window`](MovingMedian::set_window).
+    pub fn window(mut self, len: usize) -> Self {
+        self.window = len;
+        self
+    }
+
+    /// Build the filter with the chosen configuration.
+    pub fn build(self) -> MovingMedian<T, N> {
+        let mut filter = MovingMedian::new();
+        if let Some(value) = self.empty_default {
+            filter.clear_with_default(value);
+        }
+        filter.set_nan_policy(self.nan_policy);
+        filter.set_even_strategy(self.even_strategy);
+        if self.window != N {
+            filter.set_window(self.window);
+        }
+        filter
+    }
+}
+
+impl<T: Sample, const N: usize> Default for MovingMedianBuilder<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn configured_filter_uses_empty_default_until_filled() {
+        let mut filter = MovingMedianBuilder::<i32, 5>::new()
+            .empty_default(-1)
+            .window(3)
+            .build();
+        assert_eq!(filter.median(), -1);
+        assert_eq!(filter.try_median(), None);
+        assert_eq!(filter.effective_len(), 3);
+        filter.add_values(&[4, 8, 6, 2]);
+        assert!(filter.is_full());
+        assert_eq!(filter.median(), 6);
+        // Clearing drops the empty default, like for a filter built without the builder
+        filter.clear();
+        assert_eq!(filter.median(), 0);
+    }
+
+    #[test]
+    fn default_builder_matches_new() {
+        let filter = MovingMedian::<f64, 4>::builder().build();
+        assert_eq!(filter.nan_policy(), NanPolicy::Ignore);
+        assert_eq!(filter.even_strategy(), EvenStrategy::AverageMiddle);
+        assert_eq!(filter.effective_len(), 4);
+        assert_eq!(filter, MovingMedian::new());
+
+        let mut filter = MovingMedian::<f64, 4>::builder()
+            .ignore_nan(false)
+            .ignore_nan(true)
+            .build();
+        filter.add_values(&[1.0, f64::NAN, 3.0]);
+        assert_eq!(filter.median(), 2.0);
+    }
+}
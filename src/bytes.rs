@@ -0,0 +1,258 @@
+//! A dependency-free little-endian byte encoding of [`MovingMedian`] for primitive sample types.
+
+use core::fmt;
+
+use crate::{InvalidState, MovingMedian, WindowSum};
+
+// Type tag, N, index and count
+const HEADER_LEN: usize = 1 + 3 * 4;
+
+/// A primitive sample type that [`MovingMedian::write_bytes`] can encode.
+///
+/// This trait is sealed, it is implemented for `f32`, `f64`, `i16`, `i32`, `u16` and `u32` only.
+pub trait RawSample: WindowSum + sealed::Sealed {
+    /// Tag written into the header, so a blob is not read back as another type.
+    const TAG: u8;
+    /// Number of bytes of one encoded value.
+    const SIZE: usize;
+
+    /// Write the value in little-endian byte order into the first [`SIZE`](Self::SIZE) bytes of `out`.
+    fn write_le(self, out: &mut [u8]);
+
+    /// Read a value in little-endian byte order from the first [`SIZE`](Self::SIZE) bytes of `bytes`.
+    fn read_le(bytes: &[u8]) -> Self;
+}
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+macro_rules! impl_raw_sample {
+    ($($t:ty => $tag:expr),*) => {
+        $(
+            impl sealed::Sealed for $t {}
+
+            impl RawSample for $t {
+                const TAG: u8 = $tag;
+                const SIZE: usize = core::mem::size_of::<$t>();
+
+                fn write_le(self, out: &mut [u8]) {
+                    out[..Self::SIZE].copy_from_slice(&self.to_le_bytes());
+                }
+
+                fn read_le(bytes: &[u8]) -> Self {
+                    let mut raw = [0; core::mem::size_of::<$t>()];
+                    raw.copy_from_slice(&bytes[..Self::SIZE]);
+                    <$t>::from_le_bytes(raw)
+                }
+            }
+        )*
+    };
+}
+
+impl_raw_sample!(f32 => 1, f64 => 2, i16 => 3, i32 => 4, u16 => 5, u32 => 6);
+
+/// The error returned by [`MovingMedian::write_bytes`] when the output buffer cannot hold the encoding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BufferTooSmall {
+    /// Number of bytes the encoding needs, see [`MovingMedian::ENCODED_LEN`].
+    pub required: usize,
+}
+
+impl fmt::Display for BufferTooSmall {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "buffer too small, {} bytes are required", self.required)
+    }
+}
+
+impl core::error::Error for BufferTooSmall {}
+
+/// The error returned by [`MovingMedian::read_bytes`] when the bytes do not hold a matching filter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The number of bytes does not match the encoding of the filter.
+    Length(usize),
+    /// The bytes were written for another sample type, identified by its tag.
+    Type(u8),
+    /// The bytes were written for a buffer of another size N.
+    Size(usize),
+    /// The index or count does not fit the buffer.
+    State(InvalidState),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Length(len) => write!(f, "{} bytes do not match the encoded length", len),
+            Self::Type(tag) => write!(
+                f,
+                "bytes were written for another sample type (tag {})",
+                tag
+            ),
+            Self::Size(size) => write!(f, "bytes were written for a buffer of size {}", size),
+            Self::State(state) => write!(f, "invalid filter state: {}", state),
+        }
+    }
+}
+
+impl core::error::Error for DecodeError {}
+
+impl From<InvalidState> for DecodeError {
+    fn from(state: InvalidState) -> Self {
+        Self::State(state)
+    }
+}
+
+impl<T: RawSample, const N: usize> MovingMedian<T, N> {
+    /// Number of bytes written by [`write_bytes`](Self::write_bytes): a header of 13 bytes followed by the N values.
+    pub const ENCODED_LEN: usize = HEADER_LEN + N * T::SIZE;
+
+    /// Encode the filter into `out` and return the number of bytes written, for logging or storing
+    /// its state without serde. The header holds a type tag, N, the index and the count, followed by
+    /// the buffer, all in little-endian byte order. The state is the one of [`to_parts`](Self::to_parts),
+    /// so the NaN policy, even strategy and a window shortened with [`set_window`](Self::set_window)
+    /// are not included.
+    ///
+    /// ```
+    /// use moving_median::MovingMedian;
+    ///
+    /// let mut filter = MovingMedian::<i16, 4>::new();
+    /// filter.add_values(&[-3, 7, 2]);
+    ///
+    /// let mut bytes = [0; MovingMedian::<i16, 4>::ENCODED_LEN];
+    /// let len = filter.write_bytes(&mut bytes).unwrap();
+    /// let restored = MovingMedian::<i16, 4>::read_bytes(&bytes[..len]).unwrap();
+    /// assert_eq!(restored, filter);
+    /// ```
+    pub fn write_bytes(&self, out: &mut [u8]) -> Result<usize, BufferTooSmall> {
+        let required = Self::ENCODED_LEN;
+        if out.len() < required {
+            return Err(BufferTooSmall { required });
+        }
+        let (buffer, index, count) = self.to_parts();
+        out[0] = T::TAG;
+        for (i, field) in [N, index, count].into_iter().enumerate() {
+            out[1 + 4 * i..5 + 4 * i].copy_from_slice(&(field as u32).to_le_bytes());
+        }
+        for (value, chunk) in buffer
+            .iter()
+            .zip(out[HEADER_LEN..required].chunks_exact_mut(T::SIZE))
+        {
+            value.write_le(chunk);
+        }
+        Ok(required)
+    }
+
+    /// Decode a filter written by [`write_bytes`](Self::write_bytes).
+    /// Returns an error if the bytes were written for another sample type or buffer size, have the
+    /// wrong length, or hold an index or count that does not fit the buffer.
+    pub fn read_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        if bytes.len() < HEADER_LEN {
+            return Err(DecodeError::Length(bytes.len()));
+        }
+        if bytes[0] != T::TAG {
+            return Err(DecodeError::Type(bytes[0]));
+        }
+        let field = |i: usize| {
+            let mut raw = [0; 4];
+            raw.copy_from_slice(&bytes[1 + 4 * i..5 + 4 * i]);
+            u32::from_le_bytes(raw) as usize
+        };
+        if field(0) != N {
+            return Err(DecodeError::Size(field(0)));
+        }
+        if bytes.len() != Self::ENCODED_LEN {
+            return Err(DecodeError::Length(bytes.len()));
+        }
+        let mut values = bytes[HEADER_LEN..].chunks_exact(T::SIZE).map(T::read_le);
+        // The length was checked, so there are exactly N values
+        let buffer = core::array::from_fn(|_| values.next().unwrap());
+        Ok(Self::from_parts(buffer, field(1), field(2))?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! round_trip {
+        ($($name:ident: $t:ty => [$($value:expr),*]),*) => {
+            $(
+                #[test]
+                fn $name() {
+                    let mut filter = MovingMedian::<$t, 3>::new();
+                    let mut bytes = [0; MovingMedian::<$t, 3>::ENCODED_LEN];
+                    for value in [$($value),*] {
+                        filter.add_value(value);
+                        let len = filter.write_bytes(&mut bytes).unwrap();
+                        assert_eq!(len, bytes.len());
+                        let restored = MovingMedian::<$t, 3>::read_bytes(&bytes).unwrap();
+                        assert_eq!(restored, filter);
+                        assert_eq!(restored.position(), filter.position());
+                    }
+                }
+            )*
+        };
+    }
+
+    round_trip! {
+        round_trip_f32: f32 => [1.5, -2.25, f32::MAX, 0.0, 3.0],
+        round_trip_f64: f64 => [1e300, -0.5, 7.0, 2.0],
+        round_trip_i16: i16 => [i16::MIN, 5, -7, i16::MAX],
+        round_trip_i32: i32 => [i32::MAX, 0, -1, 42, 9],
+        round_trip_u16: u16 => [u16::MAX, 1, 300],
+        round_trip_u32: u32 => [7, u32::MAX, 0, 12]
+    }
+
+    #[test]
+    fn header_is_little_endian() {
+        let mut filter = MovingMedian::<u16, 2>::new();
+        filter.add_values(&[0x0102, 0x0304, 0x0506]);
+        let mut bytes = [0; 17];
+        assert_eq!(filter.write_bytes(&mut bytes), Ok(17));
+        assert_eq!(bytes, [5, 2, 0, 0, 0, 1, 0, 0, 0, 2, 0, 0, 0, 6, 5, 4, 3]);
+    }
+
+    #[test]
+    fn mismatched_blobs_are_rejected() {
+        let mut bytes = [0; 64];
+        let filter = MovingMedian::<i32, 4>::from_slice(&[1, 2, 3]);
+        let len = filter.write_bytes(&mut bytes).unwrap();
+        let bytes = &bytes[..len];
+
+        assert_eq!(
+            MovingMedian::<i32, 5>::read_bytes(bytes),
+            Err(DecodeError::Size(4))
+        );
+        assert_eq!(
+            MovingMedian::<u32, 4>::read_bytes(bytes),
+            Err(DecodeError::Type(i32::TAG))
+        );
+        assert_eq!(
+            MovingMedian::<i32, 4>::read_bytes(&bytes[..len - 1]),
+            Err(DecodeError::Length(len - 1))
+        );
+        assert_eq!(
+            MovingMedian::<i32, 4>::read_bytes(&bytes[..3]),
+            Err(DecodeError::Length(3))
+        );
+
+        let mut corrupt = [0; 29];
+        corrupt.copy_from_slice(bytes);
+        corrupt[9] = 9;
+        assert_eq!(
+            MovingMedian::<i32, 4>::read_bytes(&corrupt),
+            Err(DecodeError::State(InvalidState::Count(9)))
+        );
+    }
+
+    #[test]
+    fn small_output_buffer_is_rejected() {
+        let filter = MovingMedian::<f64, 2>::new();
+        let mut bytes = [0; 28];
+        assert_eq!(
+            filter.write_bytes(&mut bytes),
+            Err(BufferTooSmall { required: 29 })
+        );
+    }
+}
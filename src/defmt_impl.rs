@@ -0,0 +1,36 @@
+//! `defmt` support for [`MovingMedian`], behind the `defmt` feature.
+
+use crate::{MovingMedian, Sample};
+
+/// Shows the values in the buffer from oldest to newest, the count and the current median.
+impl<T: Sample + defmt::Format, const N: usize> defmt::Format for MovingMedian<T, N> {
+    fn format(&self, f: defmt::Formatter<'_>) {
+        defmt::write!(f, "MovingMedian {{ window: [");
+        for (i, value) in self.iter().enumerate() {
+            if i > 0 {
+                defmt::write!(f, ", ");
+            }
+            defmt::write!(f, "{}", value);
+        }
+        defmt::write!(
+            f,
+            "], count: {}, median: {} }}",
+            self.count,
+            self.try_median()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_format<T: defmt::Format>() {}
+
+    #[test]
+    fn format_is_implemented_for_primitive_sample_types() {
+        assert_format::<MovingMedian<f32, 8>>();
+        assert_format::<MovingMedian<i32, 5>>();
+        assert_format::<MovingMedian<u16, 3>>();
+    }
+}
@@ -0,0 +1,219 @@
+//! A moving median filter whose window size is chosen at runtime, behind the `alloc` feature.
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::{insert_sorted, middle, remove_sorted, Sample};
+
+/// A moving median filter with a heap-allocated buffer whose size is chosen at runtime.
+///
+/// This is the counterpart of [`MovingMedian`](crate::MovingMedian) for hosts where the window size
+/// comes from configuration rather than a constant, and the window can be [`resize`](Self::resize)d later.
+/// NaN values are kept in the window but left out of the median, like [`NanPolicy::Ignore`](crate::NanPolicy::Ignore).
+///
+/// ```
+/// use moving_median::MovingMedianDyn;
+///
+/// let mut filter = MovingMedianDyn::<f32>::with_capacity(3);
+/// filter.add_value(42.0);
+/// filter.add_value(43.0);
+/// filter.add_value(41.0);
+///
+/// assert_eq!(filter.median(), 42.0);
+/// ```
+#[derive(Clone)]
+pub struct MovingMedianDyn<T> {
+    // Heap-allocated buffer to hold the measurements
+    buffer: Vec<T>,
+    // Current index in the buffer
+    index: usize,
+    // Number of values added (up to the capacity)
+    count: usize,
+    // The values in the buffer other than NaN, in ascending order
+    sorted: Vec<T>,
+}
+
+impl<T: Sample> MovingMedianDyn<T> {
+    /// Create a new moving median filter with a buffer of size `n`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is zero.
+    pub fn with_capacity(n: usize) -> Self {
+        assert!(n > 0, "the window size must be at least 1");
+        Self {
+            buffer: vec![T::zero(); n],
+            index: 0,
+            count: 0,
+            sorted: Vec::with_capacity(n),
+        }
+    }
+
+    /// Add a new measurement to the buffer.
+    /// If the buffer is full, the oldest value will be replaced.
+    /// Returns the replaced oldest value, or `None` while the buffer is still filling up.
+    pub fn add_value(&mut self, value: T) -> Option<T> {
+        let evicted = if self.count == self.buffer.len() {
+            let oldest = self.buffer[self.index];
+            self.remove_sorted(oldest);
+            Some(oldest)
+        } else {
+            self.count += 1;
+            None
+        };
+        self.insert_sorted(value);
+        self.buffer[self.index] = value;
+        // Move to the next index, wrapping around if necessary
+        self.index = (self.index + 1) % self.buffer.len();
+        evicted
+    }
+
+    /// Remove all values from the buffer, so the filter starts over as if it was new.
+    pub fn clear(&mut self) {
+        self.index = 0;
+        self.count = 0;
+        self.sorted.clear();
+    }
+
+    /// Change the size of the buffer to `n`, keeping the most recent values that still fit.
+    /// Growing the buffer keeps all values and leaves the new slots empty.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is zero.
+    pub fn resize(&mut self, n: usize) {
+        assert!(n > 0, "the window size must be at least 1");
+        let kept = self.count.min(n);
+        let mut buffer = vec![T::zero(); n];
+        for (slot, i) in buffer.iter_mut().zip(self.count - kept..self.count) {
+            *slot = self.buffer[self.slot(i)];
+        }
+        self.buffer = buffer;
+        self.index = kept % n;
+        self.count = kept;
+        self.sorted.clear();
+        for i in 0..kept {
+            self.insert_sorted(self.buffer[i]);
+        }
+    }
+
+    /// Number of values in the buffer, at most the capacity. NaN values are included.
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    /// Returns `true` if no values have been added.
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Returns `true` once the buffer has been filled up.
+    pub fn is_full(&self) -> bool {
+        self.count == self.buffer.len()
+    }
+
+    /// Size of the buffer, which is the number of values the filter holds once it is full.
+    pub fn capacity(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Iterate over the values in the buffer, from the oldest to the newest.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &T> + ExactSizeIterator {
+        (0..self.count).map(move |i| &self.buffer[self.slot(i)])
+    }
+
+    /// Calculate the median of the values in the buffer.
+    /// If the number of values is even, the median is the average of the two middle values.
+    /// If no values have been added, zero is returned.
+    pub fn median(&self) -> T {
+        self.try_median().unwrap_or(T::zero())
+    }
+
+    /// Calculate the median of the values in the buffer, like [`median`](Self::median).
+    /// Returns `None` if the buffer holds no values other than NaN.
+    pub fn try_median(&self) -> Option<T> {
+        middle(&self.sorted, T::midpoint)
+    }
+
+    // Position in the buffer of the i-th oldest value
+    fn slot(&self, i: usize) -> usize {
+        let len = self.buffer.len();
+        (self.index + len - self.count + i) % len
+    }
+
+    // Grow the sorted vector by one slot for the value, and drop the slot again if it is NaN
+    fn insert_sorted(&mut self, value: T) {
+        let len = self.sorted.len();
+        self.sorted.push(value);
+        let len = insert_sorted(&mut self.sorted, len, value);
+        self.sorted.truncate(len);
+    }
+
+    fn remove_sorted(&mut self, value: T) {
+        let len = self.sorted.len();
+        let len = remove_sorted(&mut self.sorted, len, value);
+        self.sorted.truncate(len);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::XorShift;
+    use crate::MovingMedian;
+
+    #[test]
+    fn median_matches_moving_median() {
+        let mut rng = XorShift(0x2545_f491_4f6c_dd1d);
+        let mut dynamic = MovingMedianDyn::<i32>::with_capacity(7);
+        let mut fixed = MovingMedian::<i32, 7>::new();
+        for _ in 0..1_000 {
+            let value = (rng.next() % 50) as i32 - 25;
+            assert_eq!(dynamic.add_value(value), fixed.add_value(value));
+            assert_eq!(dynamic.try_median(), fixed.try_median());
+        }
+        dynamic.clear();
+        assert_eq!(dynamic.try_median(), None);
+    }
+
+    #[test]
+    fn shrinking_keeps_most_recent_values() {
+        let mut filter = MovingMedianDyn::<f64>::with_capacity(5);
+        for value in [1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0] {
+            filter.add_value(value);
+        }
+        filter.resize(3);
+        assert_eq!(filter.capacity(), 3);
+        assert!(filter.is_full());
+        assert_eq!(filter.iter().copied().collect::<Vec<_>>(), [5.0, 6.0, 7.0]);
+        assert_eq!(filter.median(), 6.0);
+        // The oldest kept value is evicted first
+        assert_eq!(filter.add_value(8.0), Some(5.0));
+    }
+
+    #[test]
+    fn growing_does_not_invent_values() {
+        let mut filter = MovingMedianDyn::<f64>::with_capacity(3);
+        for value in [1.0, 2.0, 3.0, 4.0] {
+            filter.add_value(value);
+        }
+        filter.resize(6);
+        assert_eq!(filter.len(), 3);
+        assert!(!filter.is_full());
+        assert_eq!(filter.iter().copied().collect::<Vec<_>>(), [2.0, 3.0, 4.0]);
+        assert_eq!(filter.median(), 3.0);
+        assert_eq!(filter.add_value(5.0), None);
+        assert_eq!(filter.median(), 3.5);
+    }
+
+    #[test]
+    fn nan_is_ignored() {
+        let mut filter = MovingMedianDyn::<f32>::with_capacity(3);
+        filter.add_value(1.0);
+        filter.add_value(f32::NAN);
+        filter.add_value(3.0);
+        assert_eq!(filter.median(), 2.0);
+        filter.resize(1);
+        assert_eq!(filter.median(), 3.0);
+    }
+}
@@ -0,0 +1,294 @@
+//! Moving minimum and maximum filters, backed by monotonic deques.
+
+use crate::Sample;
+
+/// A no-std moving minimum filter with a window of size N.
+///
+/// The filter keeps a deque of the values that can still become the minimum, in ascending order
+/// from the front. A new value removes every value at the back that is not smaller, because those
+/// leave the window earlier and can never be the minimum again. Adding a value is amortized `O(1)`
+/// and the minimum is the front of the deque, so the window is never scanned.
+/// NaN values count towards the window but are left out of the minimum.
+///
+/// ```
+/// use moving_median::MovingMin;
+///
+/// let mut filter = MovingMin::<f32, 3>::new();
+/// filter.add_value(3.0);
+/// filter.add_value(1.0);
+/// filter.add_value(2.0);
+/// assert_eq!(filter.min(), Some(1.0));
+///
+/// // 1.0 is pushed out
+/// filter.add_value(4.0);
+/// filter.add_value(5.0);
+/// assert_eq!(filter.min(), Some(2.0));
+/// ```
+#[derive(Clone)]
+pub struct MovingMin<T, const N: usize> {
+    deque: MonotonicDeque<T, N>,
+}
+
+/// A no-std moving maximum filter with a window of size N.
+///
+/// Works like [`MovingMin`] with the order reversed, so adding a value is amortized `O(1)`.
+/// NaN values count towards the window but are left out of the maximum.
+///
+/// ```
+/// use moving_median::MovingMax;
+///
+/// let mut filter = MovingMax::<i32, 3>::new();
+/// filter.add_value(3);
+/// filter.add_value(1);
+/// filter.add_value(2);
+/// assert_eq!(filter.max(), Some(3));
+///
+/// // 3 is pushed out
+/// filter.add_value(0);
+/// assert_eq!(filter.max(), Some(2));
+/// ```
+#[derive(Clone)]
+pub struct MovingMax<T, const N: usize> {
+    deque: MonotonicDeque<T, N>,
+}
+
+impl<T: Sample, const N: usize> MovingMin<T, N> {
+    /// Create a new moving minimum filter with a window of size N.
+    pub fn new() -> Self {
+        Self {
+            deque: MonotonicDeque::new(),
+        }
+    }
+
+    /// Add a new measurement to the window.
+    /// If the window is full, the oldest value will be replaced.
+    pub fn add_value(&mut self, value: T) {
+        self.deque.push(value, |back, new| back < new);
+    }
+
+    /// The smallest value in the window, or `None` if no values other than NaN have been added.
+    pub fn min(&self) -> Option<T> {
+        self.deque.front()
+    }
+
+    /// Remove all values from the window, so the filter starts over as if it was new.
+    pub fn clear(&mut self) {
+        self.deque.clear();
+    }
+
+    /// Number of values in the window, at most N. NaN values are included.
+    pub fn len(&self) -> usize {
+        self.deque.count
+    }
+
+    /// Returns `true` if no values have been added.
+    pub fn is_empty(&self) -> bool {
+        self.deque.count == 0
+    }
+
+    /// Returns `true` once N values have been added and the window has warmed up.
+    pub fn is_full(&self) -> bool {
+        self.deque.count == N
+    }
+
+    /// Size N of the window, which is the number of values the filter holds once it is full.
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+}
+
+impl<T: Sample, const N: usize> MovingMax<T, N> {
+    /// Create a new moving maximum filter with a window of size N.
+    pub fn new() -> Self {
+        Self {
+            deque: MonotonicDeque::new(),
+        }
+    }
+
+    /// Add a new measurement to the window.
+    /// If the window is full, the oldest value will be replaced.
+    pub fn add_value(&mut self, value: T) {
+        self.deque.push(value, |back, new| back > new);
+    }
+
+    /// The largest value in the window, or `None` if no values other than NaN have been added.
+    pub fn max(&self) -> Option<T> {
+        self.deque.front()
+    }
+
+    /// Remove all values from the window, so the filter starts over as if it was new.
+    pub fn clear(&mut self) {
+        self.deque.clear();
+    }
+
+    /// Number of values in the window, at most N. NaN values are included.
+    pub fn len(&self) -> usize {
+        self.deque.count
+    }
+
+    /// Returns `true` if no values have been added.
+    pub fn is_empty(&self) -> bool {
+        self.deque.count == 0
+    }
+
+    /// Returns `true` once N values have been added and the window has warmed up.
+    pub fn is_full(&self) -> bool {
+        self.deque.count == N
+    }
+
+    /// Size N of the window, which is the number of values the filter holds once it is full.
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+}
+
+impl<T: Sample, const N: usize> Default for MovingMin<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Sample, const N: usize> Default for MovingMax<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// A ring of (sequence number, value) entries whose values are monotonic from front to back
+#[derive(Clone)]
+struct MonotonicDeque<T, const N: usize> {
+    entries: [(usize, T); N],
+    // Position of the front entry in the ring
+    head: usize,
+    // Number of entries in the ring
+    kept: usize,
+    // Sequence number of the next value, wrapping around
+    next: usize,
+    // Number of values in the window (up to N)
+    count: usize,
+}
+
+impl<T: Sample, const N: usize> MonotonicDeque<T, N> {
+    fn new() -> Self {
+        Self {
+            entries: [(0, T::zero()); N],
+            head: 0,
+            kept: 0,
+            next: 0,
+            count: 0,
+        }
+    }
+
+    fn front(&self) -> Option<T> {
+        (self.kept > 0).then(|| self.entries[self.head].1)
+    }
+
+    // Add a value, keeping only the entries at the back for which `keep(entry, value)` holds
+    fn push(&mut self, value: T, keep: fn(&T, &T) -> bool) {
+        let sequence = self.next;
+        self.next = self.next.wrapping_add(1);
+        self.count = (self.count + 1).min(N);
+        // Drop the front entry once it has left the window
+        if self.kept > 0 && self.next.wrapping_sub(self.entries[self.head].0) > N {
+            self.head = (self.head + 1) % N;
+            self.kept -= 1;
+        }
+        if value.is_nan() {
+            return;
+        }
+        while self.kept > 0 {
+            let back = (self.head + self.kept - 1) % N;
+            if keep(&self.entries[back].1, &value) {
+                break;
+            }
+            self.kept -= 1;
+        }
+        self.entries[(self.head + self.kept) % N] = (sequence, value);
+        self.kept += 1;
+    }
+
+    fn clear(&mut self) {
+        self.head = 0;
+        self.kept = 0;
+        self.count = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use crate::test_util::XorShift;
+    use std::collections::VecDeque;
+
+    #[test]
+    fn min_and_max_are_none_when_no_values_added() {
+        assert_eq!(MovingMin::<f64, 3>::new().min(), None);
+        assert_eq!(MovingMax::<f64, 3>::new().max(), None);
+    }
+
+    #[test]
+    fn min_and_max_match_brute_force_for_random_values() {
+        let mut rng = XorShift(0x2545_f491_4f6c_dd1d);
+        let mut min = MovingMin::<i32, 5>::new();
+        let mut max = MovingMax::<i32, 5>::new();
+        let mut window = VecDeque::new();
+        for _ in 0..10_000 {
+            // Draw from a small range so duplicate values are common
+            let value = (rng.next() % 20) as i32 - 10;
+            min.add_value(value);
+            max.add_value(value);
+            window.push_back(value);
+            if window.len() > 5 {
+                window.pop_front();
+            }
+            assert_eq!(min.min(), window.iter().copied().min());
+            assert_eq!(max.max(), window.iter().copied().max());
+            assert_eq!(min.len(), window.len());
+        }
+    }
+
+    #[test]
+    fn monotonic_sequences_wrap_the_deque() {
+        // A falling sequence keeps every value in the deque of the minimum, so its ring wraps
+        let mut min = MovingMin::<i32, 4>::new();
+        let mut max = MovingMax::<i32, 4>::new();
+        for value in (0..50).rev() {
+            min.add_value(value);
+            max.add_value(value);
+            assert_eq!(min.min(), Some(value));
+            assert_eq!(max.max(), Some((value + 3).min(49)));
+        }
+        // And a rising one does the same for the maximum
+        for value in 0..50 {
+            min.add_value(value);
+            max.add_value(value);
+            // The window still holds 2 and 1 from the falling sequence at first
+            let expected = if value == 0 { 2 } else { value };
+            assert_eq!(max.max(), Some(expected));
+        }
+        assert_eq!(min.min(), Some(46));
+    }
+
+    #[test]
+    fn nan_is_ignored() {
+        let mut filter = MovingMin::<f32, 2>::new();
+        filter.add_value(1.0);
+        filter.add_value(f32::NAN);
+        assert_eq!(filter.min(), Some(1.0));
+        filter.add_value(f32::NAN);
+        assert_eq!(filter.min(), None);
+        assert_eq!(filter.len(), 2);
+    }
+
+    #[test]
+    fn clear_starts_over() {
+        let mut filter = MovingMax::<u8, 3>::new();
+        filter.add_value(9);
+        filter.clear();
+        assert!(filter.is_empty());
+        filter.add_value(2);
+        assert_eq!(filter.max(), Some(2));
+    }
+}
@@ -0,0 +1,167 @@
+//! A median kernel for two-dimensional data, such as small grayscale images.
+
+use core::marker::PhantomData;
+
+use crate::{median_of_mut, Sample};
+
+/// A no-std median filter with a square `W x W` kernel for row-major two-dimensional data, such as
+/// the pixels of a grayscale image. It removes salt-and-pepper noise while keeping edges sharp.
+///
+/// Every output pixel is the median of the `W x W` input pixels centered on it, found like
+/// [`median_of_mut`] finds it. Near the border the kernel reaches outside of the image, and the
+/// missing pixels are replaced by the nearest pixel of the edge, so the edges are replicated and
+/// every median is taken over `W x W` values. NaN values are left out of the median, and a kernel
+/// of nothing but NaN values keeps the input pixel. The kernel is kept on the stack, so no
+/// allocator is needed.
+///
+/// W must be odd, so the kernel has a center pixel, otherwise the filter does not compile:
+///
+/// ```compile_fail
+/// use moving_median::MedianFilter2D;
+///
+/// let filter = MedianFilter2D::<u8, 4>::new();
+/// ```
+///
+/// ```
+/// use moving_median::MedianFilter2D;
+///
+/// #[rustfmt::skip]
+/// let image = [
+///     10, 10, 10, 10,
+///     10, 255, 10, 10,
+///     10, 10, 90, 90,
+///     10, 10, 90, 90,
+/// ];
+/// let mut output = [0; 16];
+/// MedianFilter2D::<u8, 3>::new().apply(&image, 4, 4, &mut output);
+///
+/// #[rustfmt::skip]
+/// assert_eq!(output, [
+///     10, 10, 10, 10,
+///     10, 10, 10, 10,
+///     10, 10, 90, 90,
+///     10, 10, 90, 90,
+/// ]);
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct MedianFilter2D<T, const W: usize> {
+    sample: PhantomData<T>,
+}
+
+impl<T: Sample, const W: usize> MedianFilter2D<T, W> {
+    /// Create a new median filter with a `W x W` kernel.
+    pub const fn new() -> Self {
+        const {
+            assert!(
+                W % 2 == 1,
+                "the kernel of a MedianFilter2D needs an odd size"
+            )
+        };
+        Self {
+            sample: PhantomData,
+        }
+    }
+
+    /// Size W of the kernel, which is the width and height of the neighbourhood of every pixel.
+    pub const fn kernel_size(&self) -> usize {
+        W
+    }
+
+    /// Filter the row-major image `input` of `width x height` pixels, writing the filtered image
+    /// into the start of `output` in the same layout.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `input` does not hold exactly `width * height` pixels, or `output` is shorter.
+    pub fn apply(&self, input: &[T], width: usize, height: usize, output: &mut [T]) {
+        let len = width
+            .checked_mul(height)
+            .expect("the image size overflows usize");
+        assert_eq!(
+            input.len(),
+            len,
+            "the input does not hold width * height pixels"
+        );
+        assert!(output.len() >= len, "the output is shorter than the input");
+        let radius = W / 2;
+        let mut kernel = [[T::zero(); W]; W];
+        for y in 0..height {
+            for x in 0..width {
+                for (dy, row) in kernel.iter_mut().enumerate() {
+                    // Clamp the kernel to the image, which replicates the edge pixels
+                    let source = (y + dy).saturating_sub(radius).min(height - 1);
+                    for (dx, value) in row.iter_mut().enumerate() {
+                        let column = (x + dx).saturating_sub(radius).min(width - 1);
+                        *value = input[source * width + column];
+                    }
+                }
+                let pixel = input[y * width + x];
+                output[y * width + x] = median_of_mut(kernel.as_flattened_mut()).unwrap_or(pixel);
+            }
+        }
+    }
+}
+
+impl<T: Sample, const W: usize> Default for MedianFilter2D<T, W> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn salt_noise_pixel_is_removed() {
+        let mut image = [40u8; 25];
+        image[2 * 5 + 2] = 255;
+        let mut output = [0; 25];
+        MedianFilter2D::<u8, 3>::new().apply(&image, 5, 5, &mut output);
+        assert_eq!(output, [40; 25]);
+    }
+
+    #[test]
+    fn edges_are_replicated() {
+        // A bright corner pixel counts four times in its own kernel, which is not enough
+        #[rustfmt::skip]
+        let image = [
+            9.0, 1.0, 1.0,
+            1.0, 1.0, 1.0,
+        ];
+        let mut output = [0.0; 6];
+        MedianFilter2D::<f32, 3>::new().apply(&image, 3, 2, &mut output);
+        assert_eq!(output, [1.0; 6]);
+
+        // Together with its replicated neighbours a bright corner is kept
+        #[rustfmt::skip]
+        let image = [
+            9, 9, 1,
+            9, 1, 1,
+        ];
+        let mut output = [0; 6];
+        MedianFilter2D::<i32, 3>::new().apply(&image, 3, 2, &mut output);
+        assert_eq!(output, image);
+    }
+
+    #[test]
+    fn nan_pixels_are_left_out() {
+        let mut image = [f64::NAN; 9];
+        image[0] = 2.0;
+        image[8] = 4.0;
+        let mut output = [0.0; 9];
+        MedianFilter2D::<f64, 1>::new().apply(&image, 3, 3, &mut output);
+        assert_eq!(output[0], 2.0);
+        assert!(output[4].is_nan());
+        MedianFilter2D::<f64, 3>::new().apply(&image, 3, 3, &mut output);
+        assert_eq!(output[0], 2.0);
+        assert_eq!(output[4], 3.0);
+        assert_eq!(output[8], 4.0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn mismatched_image_size_panics() {
+        MedianFilter2D::<u8, 3>::new().apply(&[0; 8], 3, 3, &mut [0; 9]);
+    }
+}
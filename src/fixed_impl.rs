@@ -0,0 +1,136 @@
+//! [`Sample`] for the fixed-point types of the `fixed` crate, behind the `fixed` feature.
+
+use fixed::types::extra::{LeEqU128, LeEqU16, LeEqU32, LeEqU64, LeEqU8};
+use fixed::{
+    FixedI128, FixedI16, FixedI32, FixedI64, FixedI8, FixedU128, FixedU16, FixedU32, FixedU64,
+    FixedU8,
+};
+
+use crate::Sample;
+
+// Fixed-point numbers are integers scaled by a power of two, so every operation works on the
+// underlying integer and is as exact as it is for the integer: the midpoint of two values is a
+// halving by shift, rounded towards negative infinity by one LSB at most.
+macro_rules! impl_sample_for_fixed {
+    ($($t:ident<$le:ident> => $bits:ty),*) => {
+        $(
+            impl<Frac: $le> Sample for $t<Frac> {
+                type Accumulator = <$bits as Sample>::Accumulator;
+
+                fn zero() -> Self {
+                    Self::ZERO
+                }
+
+                fn midpoint(self, other: Self) -> Self {
+                    Self::from_bits(Sample::midpoint(self.to_bits(), other.to_bits()))
+                }
+
+                fn midpoint_ceil(self, other: Self) -> Self {
+                    Self::from_bits(Sample::midpoint_ceil(self.to_bits(), other.to_bits()))
+                }
+
+                fn is_nan(self) -> bool {
+                    false
+                }
+
+                fn lerp(self, other: Self, t: f64) -> Self {
+                    Self::from_bits(Sample::lerp(self.to_bits(), other.to_bits(), t))
+                }
+
+                fn abs_diff(self, other: Self) -> Self {
+                    Self::from_bits(Sample::abs_diff(self.to_bits(), other.to_bits()))
+                }
+
+                fn accumulate(self) -> Self::Accumulator {
+                    self.to_bits().accumulate()
+                }
+
+                fn mean(sum: Self::Accumulator, count: usize) -> Self {
+                    Self::from_bits(<$bits>::mean(sum, count))
+                }
+
+                fn to_f64(self) -> f64 {
+                    self.to_num()
+                }
+
+                fn from_f64(value: f64) -> Self {
+                    // Dividing by the power of two of one LSB is exact
+                    Self::from_bits(<$bits>::from_f64(value / Self::DELTA.to_num::<f64>()))
+                }
+            }
+        )*
+    };
+}
+
+impl_sample_for_fixed!(
+    FixedI8<LeEqU8> => i8, FixedI16<LeEqU16> => i16, FixedI32<LeEqU32> => i32,
+    FixedI64<LeEqU64> => i64, FixedI128<LeEqU128> => i128,
+    FixedU8<LeEqU8> => u8, FixedU16<LeEqU16> => u16, FixedU32<LeEqU32> => u32,
+    FixedU64<LeEqU64> => u64, FixedU128<LeEqU128> => u128
+);
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use crate::test_util::XorShift;
+    use crate::MovingMedian;
+    use fixed::types::{I16F16, U8F8};
+    use std::collections::VecDeque;
+    use std::vec::Vec;
+
+    #[test]
+    fn median_matches_f64_reference_within_one_lsb() {
+        let mut rng = XorShift(0x2545_f491_4f6c_dd1d);
+        let mut filter = MovingMedian::<I16F16, 9>::new();
+        let mut window = VecDeque::new();
+        for _ in 0..5_000 {
+            let value = I16F16::from_bits((rng.next() >> 32) as i32);
+            filter.add_value(value);
+            window.push_back(value.to_num::<f64>());
+            if window.len() > 9 {
+                window.pop_front();
+            }
+            let mut sorted: Vec<f64> = window.iter().copied().collect();
+            sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            let mid = sorted.len() / 2;
+            let expected = if sorted.len().is_multiple_of(2) {
+                (sorted[mid - 1] + sorted[mid]) / 2.0
+            } else {
+                sorted[mid]
+            };
+            let error = (filter.median().to_num::<f64>() - expected).abs();
+            assert!(
+                error <= I16F16::DELTA.to_num::<f64>(),
+                "{} vs {}",
+                filter.median(),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn even_median_halves_exactly_without_overflow() {
+        let mut filter = MovingMedian::<I16F16, 2>::new();
+        filter.add_values(&[I16F16::MAX, I16F16::MAX - I16F16::DELTA]);
+        assert_eq!(filter.median(), I16F16::MAX - I16F16::DELTA);
+        assert_eq!(filter.median_ceil(), Some(I16F16::MAX));
+
+        let mut filter = MovingMedian::<U8F8, 4>::new();
+        filter.add_values(&[U8F8::from_num(1.5), U8F8::from_num(2), U8F8::from_num(0.25)]);
+        assert_eq!(filter.median(), U8F8::from_num(1.5));
+        assert_eq!(filter.mean(), U8F8::from_num(1.25));
+        filter.add_value(U8F8::from_num(3));
+        assert_eq!(filter.median(), U8F8::from_num(1.75));
+    }
+
+    #[test]
+    fn statistics_convert_through_f64() {
+        let mut filter = MovingMedian::<I16F16, 4>::new();
+        filter.add_values(&[-1.0, 1.0, -1.0, 1.0].map(I16F16::from_num));
+        assert_eq!(filter.variance(), Some(I16F16::ONE));
+        assert_eq!(filter.range(), Some(I16F16::from_num(2)));
+        assert_eq!(I16F16::from_f64(-0.3), I16F16::from_bits(-19_661));
+    }
+}
@@ -0,0 +1,136 @@
+//! A Hampel outlier filter built on top of the moving median.
+
+use crate::{MovingMedian, Sample};
+
+/// A no-std Hampel filter, which replaces outliers by the median of the last N values.
+///
+/// A value is an outlier if it differs from the median of the window by more than `k` times the
+/// median absolute deviation (MAD) of the window, see [`MovingMedian::mad`]. Outliers are replaced by
+/// the median, all other values pass through unchanged. Every value is added to the window as it was
+/// measured, so a lasting step in the signal is followed once it fills half of the window.
+///
+/// `k` is in units of the raw MAD. For normally distributed noise the standard deviation is about
+/// 1.4826 times the MAD, so a `k` of about 4.5 rejects values more than three standard deviations out.
+///
+/// Values pass through while the window is filling up, and while the MAD is zero, so a window of
+/// identical values does not reject every change. NaN values are never rejected.
+///
+/// ```
+/// use moving_median::HampelFilter;
+///
+/// let mut filter = HampelFilter::<f32, 5>::new(3.0);
+/// for value in [10.0, 11.0, 10.0, 12.0, 11.0] {
+///     assert_eq!(filter.filter(value), value);
+/// }
+/// // A spike is replaced by the median of the window
+/// assert_eq!(filter.filter(100.0), 11.0);
+/// assert_eq!(filter.rejected_count(), 1);
+/// ```
+#[derive(Clone)]
+pub struct HampelFilter<T: Sample, const N: usize> {
+    // The last N values as they were measured
+    window: MovingMedian<T, N>,
+    // Number of MADs a value may be away from the median
+    k: T,
+    // Number of values replaced by the median so far
+    rejected: usize,
+}
+
+impl<T: Sample, const N: usize> HampelFilter<T, N> {
+    /// Create a new Hampel filter that rejects values more than `k` MADs away from the median.
+    pub fn new(k: T) -> Self {
+        Self {
+            window: MovingMedian::new(),
+            k,
+            rejected: 0,
+        }
+    }
+
+    /// Filter a new measurement, returning it unchanged or the median of the window if it is an outlier.
+    pub fn filter(&mut self, value: T) -> T {
+        let output = match self.outlier_replacement(value) {
+            Some(median) => {
+                self.rejected += 1;
+                median
+            }
+            None => value,
+        };
+        self.window.add_value(value);
+        output
+    }
+
+    /// Number of values that were replaced by the median so far.
+    pub fn rejected_count(&self) -> usize {
+        self.rejected
+    }
+
+    /// Remove all values from the window and reset the rejected count, so the filter starts over as if it was new.
+    pub fn clear(&mut self) {
+        self.window.clear();
+        self.rejected = 0;
+    }
+
+    // The median to output instead of `value` if it is an outlier
+    fn outlier_replacement(&self, value: T) -> Option<T> {
+        if !self.window.is_full() {
+            return None;
+        }
+        if self.window.mad()? == T::zero() {
+            return None;
+        }
+        self.window
+            .is_outlier(value, self.k)
+            .then(|| self.window.median())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use crate::test_util::XorShift;
+    use std::vec::Vec;
+
+    #[test]
+    fn spikes_are_replaced_and_clean_values_pass() {
+        let mut rng = XorShift(0x2545_f491_4f6c_dd1d);
+        let mut filter = HampelFilter::<i32, 7>::new(5);
+        let mut spikes = 0;
+        for i in 0..1_000 {
+            // A triangle wave rising from 0 to 4 and back
+            let phase = i % 8;
+            let clean = phase.min(8 - phase);
+            if rng.next().is_multiple_of(20) {
+                let output = filter.filter(clean + 1_000);
+                assert!((0..=4).contains(&output), "spike passed as {}", output);
+                spikes += 1;
+            } else {
+                assert_eq!(filter.filter(clean), clean);
+            }
+        }
+        assert_eq!(filter.rejected_count(), spikes);
+    }
+
+    #[test]
+    fn values_pass_during_warm_up() {
+        let mut filter = HampelFilter::<f64, 4>::new(2.0);
+        let outputs: Vec<f64> = [1.0, 1.0, 1000.0, 1.0]
+            .map(|value| filter.filter(value))
+            .to_vec();
+        assert_eq!(outputs, [1.0, 1.0, 1000.0, 1.0]);
+        assert_eq!(filter.rejected_count(), 0);
+    }
+
+    #[test]
+    fn identical_window_does_not_reject_changes() {
+        let mut filter = HampelFilter::<f64, 4>::new(3.0);
+        for _ in 0..4 {
+            filter.filter(5.0);
+        }
+        assert_eq!(filter.filter(6.0), 6.0);
+        assert_eq!(filter.rejected_count(), 0);
+        filter.clear();
+        assert_eq!(filter.filter(7.0), 7.0);
+    }
+}
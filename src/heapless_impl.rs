@@ -0,0 +1,154 @@
+//! A moving median filter stored in `heapless` vectors, behind the `heapless` feature.
+
+use heapless::Vec;
+
+use crate::{insert_sorted, middle, remove_sorted, Sample};
+
+/// A no-std moving median filter that keeps its window in a [`heapless::Vec`] of capacity N.
+///
+/// The window is kept in chronological order, so it can be handed to code that already works with
+/// `heapless` through [`as_vec`](Self::as_vec) and [`into_vec`](Self::into_vec) without copying.
+/// The median is computed like [`MovingMedian`](crate::MovingMedian) does, from a second vector
+/// that keeps the values in ascending order. NaN values are kept in the window but left out of the
+/// median, like [`NanPolicy::Ignore`](crate::NanPolicy::Ignore).
+///
+/// ```
+/// use moving_median::MovingMedianHeapless;
+///
+/// let mut filter = MovingMedianHeapless::<f32, 3>::new();
+/// filter.add_value(42.0);
+/// filter.add_value(43.0);
+/// filter.add_value(41.0);
+/// filter.add_value(44.0);
+///
+/// assert_eq!(filter.median(), 43.0);
+/// assert_eq!(filter.as_vec().as_slice(), [43.0, 41.0, 44.0]);
+/// ```
+#[derive(Clone)]
+pub struct MovingMedianHeapless<T, const N: usize> {
+    // The measurements, from the oldest to the newest
+    window: Vec<T, N>,
+    // The measurements other than NaN, in ascending order
+    sorted: Vec<T, N>,
+}
+
+impl<T: Sample, const N: usize> MovingMedianHeapless<T, N> {
+    /// Create a new moving median filter with a fixed-size buffer of size N.
+    pub const fn new() -> Self {
+        Self {
+            window: Vec::new(),
+            sorted: Vec::new(),
+        }
+    }
+
+    /// Add a new measurement to the buffer.
+    /// If the buffer is full, the oldest value will be replaced.
+    /// Returns the replaced oldest value, or `None` while the buffer is still filling up.
+    pub fn add_value(&mut self, value: T) -> Option<T> {
+        let evicted = if self.window.is_full() {
+            let oldest = self.window.remove(0);
+            let len = self.sorted.len();
+            let len = remove_sorted(&mut self.sorted, len, oldest);
+            self.sorted.truncate(len);
+            Some(oldest)
+        } else {
+            None
+        };
+        // Grow the sorted vector by one slot for the value, and drop the slot again if it is NaN.
+        // Cannot fail, the sorted vector never holds more values than the window
+        let len = self.sorted.len();
+        let _ = self.sorted.push(value);
+        let len = insert_sorted(&mut self.sorted, len, value);
+        self.sorted.truncate(len);
+        let _ = self.window.push(value);
+        evicted
+    }
+
+    /// Remove all values from the buffer, so the filter starts over as if it was new.
+    pub fn clear(&mut self) {
+        self.window.clear();
+        self.sorted.clear();
+    }
+
+    /// Number of values in the buffer, at most N. NaN values are included.
+    pub fn len(&self) -> usize {
+        self.window.len()
+    }
+
+    /// Returns `true` if no values have been added.
+    pub fn is_empty(&self) -> bool {
+        self.window.is_empty()
+    }
+
+    /// Returns `true` once N values have been added and the buffer has warmed up.
+    pub fn is_full(&self) -> bool {
+        self.window.is_full()
+    }
+
+    /// Size N of the buffer, which is the number of values the filter holds once it is full.
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
+    /// The values in the buffer, from the oldest to the newest.
+    pub fn as_vec(&self) -> &Vec<T, N> {
+        &self.window
+    }
+
+    /// Consume the filter and return the values in the buffer, from the oldest to the newest.
+    pub fn into_vec(self) -> Vec<T, N> {
+        self.window
+    }
+
+    /// Calculate the median of the values in the buffer.
+    /// If the number of values is even, the median is the average of the two middle values.
+    /// If no values have been added, zero is returned.
+    pub fn median(&self) -> T {
+        self.try_median().unwrap_or(T::zero())
+    }
+
+    /// Calculate the median of the values in the buffer, like [`median`](Self::median).
+    /// Returns `None` if the buffer holds no values other than NaN.
+    pub fn try_median(&self) -> Option<T> {
+        middle(&self.sorted, T::midpoint)
+    }
+}
+
+impl<T: Sample, const N: usize> Default for MovingMedianHeapless<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::XorShift;
+    use crate::MovingMedian;
+
+    #[test]
+    fn into_vec_is_in_chronological_order_after_wraparound() {
+        let mut filter = MovingMedianHeapless::<i32, 4>::new();
+        for value in 1..=10 {
+            filter.add_value(value);
+        }
+        let window = filter.into_vec();
+        assert_eq!(window.as_slice(), [7, 8, 9, 10]);
+    }
+
+    #[test]
+    fn median_matches_moving_median() {
+        let mut rng = XorShift(0x9e37_79b9_7f4a_7c15);
+        let mut heapless = MovingMedianHeapless::<f64, 5>::new();
+        let mut fixed = MovingMedian::<f64, 5>::new();
+        for _ in 0..1_000 {
+            let value = match rng.next() % 20 {
+                0 => f64::NAN,
+                value => value as f64,
+            };
+            let evicted = heapless.add_value(value).map(f64::to_bits);
+            assert_eq!(evicted, fixed.add_value(value).map(f64::to_bits));
+            assert_eq!(heapless.try_median(), fixed.try_median());
+        }
+    }
+}
@@ -0,0 +1,139 @@
+//! An iterator adapter that yields the moving median of the items of any iterator.
+
+use core::iter::FusedIterator;
+
+use crate::{MovingMedian, Sample};
+
+/// An extension trait for iterators, which adds the [`moving_median`](Self::moving_median) adapter,
+/// also available as [`rolling_median`](Self::rolling_median).
+///
+/// ```
+/// use moving_median::MovingMedianExt;
+///
+/// let samples = [5, 1, 9, 2, 7];
+/// let medians = samples.iter().copied().moving_median::<3>();
+/// assert!(medians.eq([5, 3, 5, 2, 7]));
+/// ```
+pub trait MovingMedianExt: Iterator {
+    /// Run an N-wide [`MovingMedian`] across the items, yielding the median after each one.
+    /// The first N - 1 medians are those of the partially filled window, like
+    /// [`windowed_medians`](crate::windowed_medians) gives them. NaN values are left out of the median,
+    /// and a window of nothing but NaN values has a median of zero.
+    fn moving_median<const N: usize>(self) -> MovingMedianIter<Self, N>
+    where
+        Self: Sized,
+        Self::Item: Sample,
+    {
+        MovingMedianIter {
+            iter: self,
+            filter: MovingMedian::new(),
+        }
+    }
+
+    /// Another name for [`moving_median`](Self::moving_median), as other crates call the adapter.
+    fn rolling_median<const N: usize>(self) -> MovingMedianIter<Self, N>
+    where
+        Self: Sized,
+        Self::Item: Sample,
+    {
+        self.moving_median()
+    }
+}
+
+impl<I: Iterator> MovingMedianExt for I {}
+
+/// The iterator returned by [`MovingMedianExt::moving_median`].
+#[derive(Clone)]
+pub struct MovingMedianIter<I: Iterator, const N: usize>
+where
+    I::Item: Sample,
+{
+    iter: I,
+    filter: MovingMedian<I::Item, N>,
+}
+
+impl<I: Iterator, const N: usize> MovingMedianIter<I, N>
+where
+    I::Item: Sample,
+{
+    /// The filter holding the window of the last N items, for other statistics than the median.
+    pub fn as_filter(&self) -> &MovingMedian<I::Item, N> {
+        &self.filter
+    }
+}
+
+impl<I: Iterator, const N: usize> Iterator for MovingMedianIter<I, N>
+where
+    I::Item: Sample,
+{
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.filter.add_value(self.iter.next()?);
+        Some(self.filter.median())
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<I: ExactSizeIterator, const N: usize> ExactSizeIterator for MovingMedianIter<I, N> where
+    I::Item: Sample
+{
+}
+
+impl<I: FusedIterator, const N: usize> FusedIterator for MovingMedianIter<I, N> where I::Item: Sample
+{}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use crate::test_util::XorShift;
+    use std::vec::Vec;
+
+    #[test]
+    fn adapter_matches_manual_loop() {
+        let mut rng = XorShift(0x2545_f491_4f6c_dd1d);
+        let samples: [f64; 500] = core::array::from_fn(|_| match rng.next() % 32 {
+            0 => f64::NAN,
+            value => value as f64 - 16.0,
+        });
+        let mut filter = MovingMedian::<f64, 7>::new();
+        let mut medians = samples.iter().copied().moving_median::<7>();
+        assert_eq!(medians.len(), samples.len());
+        for &value in &samples {
+            filter.add_value(value);
+            assert_eq!(medians.next(), Some(filter.median()));
+        }
+        assert_eq!(medians.next(), None);
+        assert_eq!(medians.as_filter(), &filter);
+    }
+
+    #[test]
+    fn range_collects_like_stepped_filter() {
+        let medians = (1..=5).rolling_median::<3>().collect::<Vec<_>>();
+        let mut filter = MovingMedian::<i32, 3>::new();
+        let stepped = (1..=5)
+            .map(|value| {
+                filter.add_value(value);
+                filter.median()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(medians, stepped);
+        assert_eq!(medians, [1, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn warm_up_medians_are_included() {
+        let mut medians = [10u8, 20, 0, 255].into_iter().moving_median::<8>();
+        assert_eq!(medians.next(), Some(10));
+        assert_eq!(medians.next(), Some(15));
+        assert_eq!(medians.next(), Some(10));
+        assert_eq!(medians.next(), Some(15));
+        assert_eq!(medians.next(), None);
+        assert_eq!(core::iter::empty::<i32>().moving_median::<3>().next(), None);
+    }
+}
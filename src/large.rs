@@ -0,0 +1,408 @@
+//! A moving median filter for large windows, backed by two binary heaps.
+
+use crate::Sample;
+
+/// A no-std moving median filter for large windows with a fixed-size buffer of size N.
+///
+/// The lower half of the window is kept in a max-heap and the upper half in a min-heap, so adding a
+/// value costs `O(log N)` and the median is read from the tops of the heaps. Both heaps store ring
+/// buffer indices rather than values, and a table of heap positions keyed on the ring index lets the
+/// evicted value be removed from the middle of its heap directly, so no memory beyond `[_; N]` arrays is needed.
+///
+/// [`MovingMedian`](crate::MovingMedian) shifts up to N values on every [`add_value`](Self::add_value),
+/// which is faster for small windows. Prefer this type once windows reach hundreds or thousands of values.
+/// NaN values are kept in the window but left out of the median, like [`NanPolicy::Ignore`](crate::NanPolicy::Ignore).
+///
+/// ```
+/// use moving_median::LargeMovingMedian;
+///
+/// let mut filter = LargeMovingMedian::<f32, 1024>::new();
+/// for i in 0..4096 {
+///     filter.add_value(i as f32);
+/// }
+/// // Only the last 1024 values, 3072 to 4095, are in the window
+/// assert_eq!(filter.median(), 3583.5);
+/// ```
+#[derive(Clone)]
+pub struct LargeMovingMedian<T, const N: usize> {
+    // The values, which stay in their ring slots while the heaps only move the slot indices
+    buffer: [T; N],
+    index: usize,
+    count: usize,
+    // Max-heap of the ring indices of the lower half of the values
+    low: IndexHeap<N>,
+    // Min-heap of the ring indices of the upper half of the values
+    high: IndexHeap<N>,
+    // Where each ring index is in the heaps
+    locations: Locations<N>,
+}
+
+// Which heap each ring index is in, and its position within that heap
+#[derive(Clone)]
+struct Locations<const N: usize> {
+    side: [Side; N],
+    position: [usize; N],
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Side {
+    Low,
+    High,
+    // Empty slots and NaN values are not in either heap
+    Neither,
+}
+
+impl<T: Sample, const N: usize> LargeMovingMedian<T, N> {
+    /// Create a new moving median filter with a fixed-size buffer of size N.
+    pub fn new() -> Self {
+        Self {
+            buffer: [T::zero(); N],
+            index: 0,
+            count: 0,
+            low: IndexHeap::new(Side::Low),
+            high: IndexHeap::new(Side::High),
+            locations: Locations {
+                side: [Side::Neither; N],
+                position: [0; N],
+            },
+        }
+    }
+
+    /// Add a new measurement to the buffer.
+    /// If the buffer is full, the oldest value will be replaced.
+    pub fn add_value(&mut self, value: T) {
+        // Remove the value that is about to be overwritten from its heap
+        if self.count == N {
+            self.remove(self.index);
+        } else {
+            self.count += 1;
+        }
+        self.buffer[self.index] = value;
+        self.insert(self.index);
+        // Move to the next index, wrapping around if necessary
+        self.index = (self.index + 1) % N;
+    }
+
+    /// Remove all values from the buffer, so the filter starts over as if it was new.
+    pub fn clear(&mut self) {
+        self.index = 0;
+        self.count = 0;
+        self.low.len = 0;
+        self.high.len = 0;
+        self.locations.side = [Side::Neither; N];
+    }
+
+    /// Number of values in the buffer, at most N. NaN values are included.
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    /// Returns `true` if no values have been added.
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Returns `true` once N values have been added and the buffer has warmed up.
+    pub fn is_full(&self) -> bool {
+        self.count == N
+    }
+
+    /// Size N of the buffer, which is the number of values the filter holds once it is full.
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
+    /// Calculate the median of the values in the buffer.
+    /// If the number of values is even, the median is the average of the two middle values.
+    /// If no values have been added, zero is returned.
+    pub fn median(&self) -> T {
+        self.try_median().unwrap_or(T::zero())
+    }
+
+    /// Calculate the median of the values in the buffer, like [`median`](Self::median).
+    /// Returns `None` if the buffer holds no values other than NaN.
+    pub fn try_median(&self) -> Option<T> {
+        // The lower half holds the extra value when the number of values is odd
+        let low = self.buffer[self.low.top()?];
+        if self.low.len > self.high.len {
+            Some(low)
+        } else {
+            Some(low.midpoint(self.buffer[self.high.top()?]))
+        }
+    }
+
+    // Insert the value at a ring index into the heaps and restore their balance
+    fn insert(&mut self, slot: usize) {
+        let value = self.buffer[slot];
+        if value.is_nan() {
+            self.locations.side[slot] = Side::Neither;
+            return;
+        }
+        let to_low = match self.low.top() {
+            Some(top) => value <= self.buffer[top],
+            None => true,
+        };
+        if to_low {
+            self.low.push(slot, &self.buffer, &mut self.locations);
+        } else {
+            self.high.push(slot, &self.buffer, &mut self.locations);
+        }
+        self.rebalance();
+    }
+
+    // Remove the value at a ring index from its heap and restore their balance
+    fn remove(&mut self, slot: usize) {
+        let at = self.locations.position[slot];
+        match self.locations.side[slot] {
+            Side::Low => self.low.remove(at, &self.buffer, &mut self.locations),
+            Side::High => self.high.remove(at, &self.buffer, &mut self.locations),
+            Side::Neither => return,
+        }
+        self.rebalance();
+    }
+
+    // Move the top of one heap to the other so the lower half has the same size as the upper half, or one more
+    fn rebalance(&mut self) {
+        if self.low.len > self.high.len + 1 {
+            let slot = self.low.top().unwrap();
+            self.low.remove(0, &self.buffer, &mut self.locations);
+            self.high.push(slot, &self.buffer, &mut self.locations);
+        } else if self.high.len > self.low.len {
+            let slot = self.high.top().unwrap();
+            self.high.remove(0, &self.buffer, &mut self.locations);
+            self.low.push(slot, &self.buffer, &mut self.locations);
+        }
+    }
+}
+
+impl<T: Sample, const N: usize> Default for LargeMovingMedian<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// A binary heap of ring indices ordered by the values they point to
+#[derive(Clone)]
+struct IndexHeap<const N: usize> {
+    entries: [usize; N],
+    len: usize,
+    // `Side::Low` is a max-heap, `Side::High` a min-heap
+    side: Side,
+}
+
+impl<const N: usize> IndexHeap<N> {
+    const fn new(side: Side) -> Self {
+        Self {
+            entries: [0; N],
+            len: 0,
+            side,
+        }
+    }
+
+    fn top(&self) -> Option<usize> {
+        (self.len > 0).then_some(self.entries[0])
+    }
+
+    fn push<T: PartialOrd>(&mut self, slot: usize, values: &[T; N], locations: &mut Locations<N>) {
+        locations.side[slot] = self.side;
+        self.entries[self.len] = slot;
+        locations.position[slot] = self.len;
+        self.len += 1;
+        self.sift_up(self.len - 1, values, locations);
+    }
+
+    fn remove<T: PartialOrd>(&mut self, at: usize, values: &[T; N], locations: &mut Locations<N>) {
+        locations.side[self.entries[at]] = Side::Neither;
+        self.len -= 1;
+        if at == self.len {
+            return;
+        }
+        // Fill the gap with the last entry and move it to where it belongs
+        self.entries[at] = self.entries[self.len];
+        locations.position[self.entries[at]] = at;
+        let at = self.sift_up(at, values, locations);
+        self.sift_down(at, values, locations);
+    }
+
+    // Returns `true` if entry `a` belongs closer to the top of the heap than entry `b`
+    fn before<T: PartialOrd>(&self, a: usize, b: usize, values: &[T; N]) -> bool {
+        let (a, b) = (&values[self.entries[a]], &values[self.entries[b]]);
+        match self.side {
+            Side::Low => a > b,
+            _ => a < b,
+        }
+    }
+
+    fn swap(&mut self, a: usize, b: usize, locations: &mut Locations<N>) {
+        self.entries.swap(a, b);
+        locations.position[self.entries[a]] = a;
+        locations.position[self.entries[b]] = b;
+    }
+
+    fn sift_up<T: PartialOrd>(
+        &mut self,
+        mut at: usize,
+        values: &[T; N],
+        locations: &mut Locations<N>,
+    ) -> usize {
+        while at > 0 {
+            let parent = (at - 1) / 2;
+            if !self.before(at, parent, values) {
+                break;
+            }
+            self.swap(at, parent, locations);
+            at = parent;
+        }
+        at
+    }
+
+    fn sift_down<T: PartialOrd>(
+        &mut self,
+        mut at: usize,
+        values: &[T; N],
+        locations: &mut Locations<N>,
+    ) {
+        loop {
+            let mut first = at;
+            for child in [2 * at + 1, 2 * at + 2] {
+                if child < self.len && self.before(child, first, values) {
+                    first = child;
+                }
+            }
+            if first == at {
+                break;
+            }
+            self.swap(at, first, locations);
+            at = first;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::XorShift;
+    use crate::MovingMedian;
+
+    #[test]
+    fn median_is_zero_when_no_values_added() {
+        let filter = LargeMovingMedian::<f64, 8>::new();
+        assert_eq!(filter.median(), 0.0);
+        assert_eq!(filter.try_median(), None);
+    }
+
+    #[test]
+    fn median_is_middle_value_when_more_than_n_values_added() {
+        let mut filter = LargeMovingMedian::<f64, 3>::new();
+        filter.add_value(42.0); // should be pushed out
+        filter.add_value(44.0);
+        filter.add_value(43.0); // should be the median
+        filter.add_value(41.0);
+        assert_eq!(filter.median(), 43.0);
+    }
+
+    #[test]
+    fn median_works_for_window_of_one() {
+        let mut filter = LargeMovingMedian::<i32, 1>::new();
+        for value in [5, -3, 8] {
+            filter.add_value(value);
+            assert_eq!(filter.median(), value);
+        }
+    }
+
+    #[test]
+    fn nan_is_ignored() {
+        let mut filter = LargeMovingMedian::<f32, 3>::new();
+        filter.add_value(1.0);
+        filter.add_value(f32::NAN);
+        filter.add_value(3.0);
+        assert_eq!(filter.median(), 2.0);
+        assert_eq!(filter.len(), 3);
+        filter.add_value(f32::NAN);
+        filter.add_value(f32::NAN);
+        filter.add_value(f32::NAN);
+        assert_eq!(filter.try_median(), None);
+    }
+
+    #[test]
+    fn median_matches_moving_median_for_random_values() {
+        let mut rng = XorShift(0x2545_f491_4f6c_dd1d);
+        let mut large = LargeMovingMedian::<i64, 1000>::new();
+        let mut simple = MovingMedian::<i64, 1000>::new();
+        for _ in 0..20_000 {
+            // Draw from a small range so duplicate values are common
+            let value = (rng.next() % 500) as i64 - 250;
+            large.add_value(value);
+            simple.add_value(value);
+            assert_eq!(large.try_median(), simple.try_median());
+        }
+    }
+
+    #[test]
+    fn median_matches_moving_median_for_small_windows() {
+        let mut rng = XorShift(0x9e37_79b9_7f4a_7c15);
+        let mut large = LargeMovingMedian::<f64, 6>::new();
+        let mut simple = MovingMedian::<f64, 6>::new();
+        for _ in 0..10_000 {
+            let value = (rng.next() % 10) as f64;
+            large.add_value(value);
+            simple.add_value(value);
+            assert_eq!(large.median(), simple.median());
+        }
+    }
+
+    #[test]
+    fn median_matches_brute_force_sort() {
+        let mut rng = XorShift(0xbf58_476d_1ce4_e5b9);
+        let mut filter = LargeMovingMedian::<f64, 64>::new();
+        let mut window = [0.0; 64];
+        for i in 0..50_000 {
+            let value = (rng.next() % 1_000) as f64 / 8.0;
+            filter.add_value(value);
+            window[i % 64] = value;
+            let mut sorted = window;
+            let sorted = &mut sorted[..(i + 1).min(64)];
+            sorted.sort_unstable_by(|a, b| a.partial_cmp(b).unwrap());
+            let mid = sorted.len() / 2;
+            let expected = if sorted.len() % 2 == 0 {
+                (sorted[mid - 1] + sorted[mid]) / 2.0
+            } else {
+                sorted[mid]
+            };
+            assert_eq!(filter.median(), expected);
+        }
+    }
+
+    #[test]
+    fn clone_is_independent_of_original() {
+        let mut filter = LargeMovingMedian::<f64, 3>::new();
+        filter.add_value(1.0);
+        filter.add_value(2.0);
+        filter.add_value(3.0);
+        let mut snapshot = filter.clone();
+        snapshot.add_value(100.0);
+        snapshot.add_value(200.0);
+        assert_eq!(snapshot.median(), 100.0);
+        assert_eq!(filter.median(), 2.0);
+    }
+
+    #[test]
+    fn capacity_is_n() {
+        assert_eq!(LargeMovingMedian::<f32, 1>::new().capacity(), 1);
+        assert_eq!(LargeMovingMedian::<i64, 1000>::new().capacity(), 1000);
+    }
+
+    #[test]
+    fn clear_starts_over() {
+        let mut filter = LargeMovingMedian::<i32, 4>::new();
+        for value in [9, 1, 5, 7, 3] {
+            filter.add_value(value);
+        }
+        filter.clear();
+        assert!(filter.is_empty());
+        assert_eq!(filter.try_median(), None);
+        filter.add_value(2);
+        filter.add_value(8);
+        assert_eq!(filter.median(), 5);
+    }
+}
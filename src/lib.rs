@@ -2,10 +2,25 @@
 //!
 //! A simple no-std moving median filter implementation with a fixed-size buffer.
 //! The buffer is used to store the last N measurements, where N is the size of the buffer.
-//! The median is calculated by sorting the values in the buffer and taking the middle value.
+//! A second buffer keeps the same values in ascending order, so the median is a lookup of the middle value.
 //! If the number of values is even, the median is the average of the two middle values.
 //! If the number of values is odd, the median is the middle value.
 //!
+//! Any type implementing [`Sample`] can be filtered, which covers f32, f64 and all primitive integers.
+//! Types that can only be ordered, such as timestamps, can still be filtered with
+//! [`MovingMedian::with_default`] and queried for order statistics like [`MovingMedian::median_low`].
+//!
+//! ## Features
+//!
+//! - `alloc`: `MovingMedianDyn`, whose window size is chosen at runtime and can be resized.
+//! - `defmt`: `defmt::Format` for [`MovingMedian`], showing the window from oldest to newest, the count and the median.
+//! - `fixed`: [`Sample`] for the fixed-point types of the `fixed` crate, such as `I16F16`.
+//! - `heapless`: `MovingMedianHeapless`, which keeps its window in a `heapless::Vec` in chronological order.
+//! - `libm`: `MovingMedian::std_dev`, using `libm` for the square root.
+//! - `num-traits`: `NumSample`, a wrapper that implements [`Sample`] for any scalar type that implements the `num-traits` traits.
+//! - `serde`: `Serialize` and `Deserialize` for [`MovingMedian`], keeping the crate no-std.
+//!   Deserialization rejects data whose index or count does not fit the buffer.
+//!
 //! ## Example
 //!
 //! ```
@@ -29,133 +44,3907 @@
 //!
 //! assert_eq!(filter_f64.median(), 42.0);
 //! ```
+//!
+//! ```
+//! use moving_median::MovingMedian;
+//!
+//! let mut filter_u16 = MovingMedian::<u16, 4>::new();
+//! filter_u16.add_value(512);
+//! filter_u16.add_value(515);
+//!
+//! // The average of the two middle values is rounded down for integers
+//! assert_eq!(filter_u16.median(), 513);
+//! ```
+//!
+//! An empty filter has no median. [`MovingMedian::try_median`] reports this as `None`,
+//! while [`MovingMedian::median`] falls back to zero.
+//!
+//! ```
+//! use moving_median::MovingMedian;
+//!
+//! let mut filter = MovingMedian::<f32, 3>::new();
+//! assert_eq!(filter.try_median(), None);
+//!
+//! filter.add_value(0.0);
+//! assert_eq!(filter.try_median(), Some(0.0));
+//! ```
+//!
+//! For samples with varying confidence, [`WeightedMovingMedian`] keeps a weight next to every value
+//! and returns the value at which the cumulative weight crosses half of the total weight.
+//!
+//! ```
+//! use moving_median::WeightedMovingMedian;
+//!
+//! let mut filter = WeightedMovingMedian::<f32, f32, 4>::new();
+//! filter.add_weighted(1.0, 1.0);
+//! filter.add_weighted(2.0, 1.0);
+//! filter.add_weighted(3.0, 1.0);
+//! filter.add_weighted(50.0, 2.0);
+//!
+//! // The outlier carries two fifths of the total weight, enough to pull the median up to 3.0
+//! assert_eq!(filter.median(), 3.0);
+//! ```
 
 #![no_std]
 
-use core::cmp::PartialOrd;
-use core::ops::{Add, Div};
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+use core::fmt;
+use core::iter::FusedIterator;
+use core::ops::{Add, Index};
+
+mod builder;
+mod bytes;
+#[cfg(feature = "defmt")]
+mod defmt_impl;
+#[cfg(feature = "alloc")]
+mod dynamic;
+mod extrema;
+mod filter2d;
+#[cfg(feature = "fixed")]
+mod fixed_impl;
+mod hampel;
+#[cfg(feature = "heapless")]
+mod heapless_impl;
+mod iter;
+mod large;
+mod mean;
+mod multi;
+#[cfg(feature = "num-traits")]
+mod num_traits_impl;
+mod sample;
+#[cfg(feature = "serde")]
+mod serde_impl;
+mod sliding;
+#[cfg(test)]
+mod test_util;
+mod timed;
+mod weighted;
+
+pub use builder::MovingMedianBuilder;
+pub use bytes::{BufferTooSmall, DecodeError, RawSample};
+#[cfg(feature = "alloc")]
+pub use dynamic::MovingMedianDyn;
+pub use extrema::{MovingMax, MovingMin};
+pub use filter2d::MedianFilter2D;
+pub use hampel::HampelFilter;
+#[cfg(feature = "heapless")]
+pub use heapless_impl::MovingMedianHeapless;
+pub use iter::{MovingMedianExt, MovingMedianIter};
+pub use large::LargeMovingMedian;
+pub use mean::MovingMean;
+pub use multi::MultiMovingMedian;
+#[cfg(feature = "num-traits")]
+pub use num_traits_impl::NumSample;
+pub use sample::{Sample, WindowSum};
+pub use sliding::SlidingFilter;
+pub use timed::TimedMovingMedian;
+pub use weighted::WeightedMovingMedian;
+
+/// How a [`MovingMedian`] treats values that cannot be ordered, such as NaN.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum NanPolicy {
+    /// Leave NaN values out of the median, which is calculated from the remaining values only.
+    /// A window that holds nothing but NaN values has no median.
+    #[default]
+    Ignore,
+    /// Report NaN as the median as long as at least one NaN value is in the window.
+    Propagate,
+}
+
+/// What [`MovingMedian::median`] returns for an even number of values, which has two middle values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum EvenStrategy {
+    /// The average of the two middle values, rounded towards negative infinity for integers.
+    #[default]
+    AverageMiddle,
+    /// The lower of the two middle values.
+    TakeLower,
+    /// The upper of the two middle values.
+    TakeUpper,
+}
+
+/// How [`MovingMedian::quantile`] picks a value when the requested quantile falls between two values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interpolation {
+    /// Take the value whose rank is nearest to the quantile, rounding halfway ranks up.
+    Nearest,
+    /// Interpolate linearly between the two values around the quantile, like the average of the
+    /// two middle values of the median. For integers the result is rounded towards negative infinity.
+    Linear,
+}
+
+/// The error returned by [`MovingMedian::from_parts`] and [`Snapshot::into_filter`] when the parts
+/// do not describe a valid filter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InvalidState {
+    /// The index is not less than the window length, which is the buffer size N for `from_parts`.
+    Index(usize),
+    /// The count is greater than the window length, which is the buffer size N for `from_parts`.
+    Count(usize),
+    /// The window length of a [`Snapshot`] is zero or greater than the buffer size N.
+    Window(usize),
+}
+
+impl fmt::Display for InvalidState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Index(index) => write!(f, "index {} is out of range of the buffer", index),
+            Self::Count(count) => write!(f, "count {} is greater than the buffer size", count),
+            Self::Window(len) => write!(f, "window length {} does not fit the buffer", len),
+        }
+    }
+}
+
+impl core::error::Error for InvalidState {}
+
+/// A copy of the complete state of a [`MovingMedian`] with named fields, returned by
+/// [`MovingMedian::snapshot`]. It borrows nothing, so it can be stored or logged and turned back
+/// into a filter for deterministic replay with [`into_filter`](Self::into_filter).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Snapshot<T, const N: usize> {
+    /// The ring buffer, where the window is the `count` values before `index`, wrapping around
+    /// from the end of the window length.
+    pub buffer: [T; N],
+    /// Number of values in the window.
+    pub count: usize,
+    /// Position in the buffer that the next value is written to.
+    pub index: usize,
+    /// Number of buffer slots in use, see [`MovingMedian::set_window`].
+    pub effective_len: usize,
+    /// How NaN values affect the median.
+    pub nan_policy: NanPolicy,
+    /// What the median of an even number of values is.
+    pub even_strategy: EvenStrategy,
+    /// Number of values added, see [`MovingMedian::total_pushed`].
+    pub total: u64,
+    /// The median of the empty filter, see [`MovingMedian::clear_with_default`].
+    pub empty_median: Option<T>,
+}
+
+impl<T: WindowSum, const N: usize> Snapshot<T, N> {
+    /// Restore the filter the snapshot was taken of, which continues exactly like the original.
+    /// The sorted buffer is rebuilt from the window.
+    /// Returns an error if the fields were changed so they no longer fit the buffer.
+    pub fn into_filter(self) -> Result<MovingMedian<T, N>, InvalidState> {
+        if self.effective_len == 0 || self.effective_len > N {
+            return Err(InvalidState::Window(self.effective_len));
+        }
+        if self.index >= self.effective_len {
+            return Err(InvalidState::Index(self.index));
+        }
+        if self.count > self.effective_len {
+            return Err(InvalidState::Count(self.count));
+        }
+        let mut filter = MovingMedian::with_default(self.buffer[0]);
+        filter.buffer = self.buffer;
+        filter.index = self.index;
+        filter.count = self.count;
+        filter.effective_len = self.effective_len;
+        filter.nan_policy = self.nan_policy;
+        filter.even_strategy = self.even_strategy;
+        filter.total = self.total;
+        filter.empty_median = self.empty_median;
+        for i in 0..self.count {
+            filter.insert_sorted(filter.buffer[filter.slot(i)]);
+        }
+        Ok(filter)
+    }
+}
 
 /// A simple no-std moving median filter implementation with a fixed-size buffer. The buffer is used to store the last N measurements, where N is the size of the buffer.
-/// The median is calculated by sorting the values in the buffer and taking the middle value. If the number of values is even, the median is the average of the two middle values. If the number of values is odd, the median is the middle value.
-pub struct MovingMedian<T, const N: usize> {
+/// A second buffer keeps the same values sorted in ascending order, so the median is a lookup of the middle value. If the number of values is even, the median is the average of the two middle values. If the number of values is odd, the median is the middle value.
+/// Values that cannot be ordered, such as NaN, are handled according to the [`NanPolicy`].
+///
+/// The buffer must hold at least one value, so a filter with N = 0 does not compile:
+///
+/// ```compile_fail
+/// use moving_median::MovingMedian;
+///
+/// let filter = MovingMedian::<f32, 0>::new();
+/// ```
+#[derive(Clone)]
+pub struct MovingMedian<T: WindowSum, const N: usize> {
     // Fixed-size buffer to hold the measurements
     buffer: [T; N],
     // Current index in the buffer
     index: usize,
-    // Number of values added (up to N)
+    // Number of values added (up to the effective length)
     count: usize,
+    // Number of slots in use, at most N, so the window can be shorter than the buffer
+    effective_len: usize,
+    // The ordered (non-NaN) values of the buffer, kept in ascending order
+    sorted: [T; N],
+    // Number of values in the sorted buffer
+    sorted_len: usize,
+    // How NaN values affect the median
+    nan_policy: NanPolicy,
+    // What the median of an even number of values is
+    even_strategy: EvenStrategy,
+    // Number of values added since the filter was created or cleared, wrapping around
+    total: u64,
+    // The median of an empty filter, if not zero
+    empty_median: Option<T>,
+    // Running sum of the sorted buffer, none while it is empty
+    sum: Option<T::Sum>,
 }
 
-impl<T, const N: usize> MovingMedian<T, N>
-where
-    T: Copy + PartialOrd + Add<Output = T> + Div<Output = T> + From<u8> + Default,
-{
+impl<T: Sample, const N: usize> MovingMedian<T, N> {
     /// Create a new moving median filter with a fixed-size buffer of size N.
+    /// Use [`with_default`](Self::with_default) where a `const fn` is needed.
     pub fn new() -> Self {
+        Self::with_default(T::zero())
+    }
+
+    /// Start a [`MovingMedianBuilder`] to configure the NaN policy, even strategy, window and
+    /// empty default of a new filter before it is used.
+    pub fn builder() -> MovingMedianBuilder<T, N> {
+        MovingMedianBuilder::new()
+    }
+
+    /// Create a new moving median filter that is already full, with all N slots holding `initial`.
+    /// Seeding the window with a known startup value, such as the first raw reading, avoids the
+    /// jumpy medians of a partially filled window. The seeds are replaced as new values are added.
+    pub fn new_filled(initial: T) -> Self {
+        let mut filter = Self::with_default(initial);
+        filter.reset_to(initial);
+        filter
+    }
+
+    /// Create a new moving median filter seeded with the last N values of `values`, where the
+    /// first element is the oldest value. The filter is the same as after adding each value in
+    /// turn, so later calls to [`add_value`](Self::add_value) continue the ring seamlessly.
+    ///
+    /// ```
+    /// use moving_median::MovingMedian;
+    ///
+    /// let filter = MovingMedian::<i32, 3>::from_slice(&[5, 1, 4, 2]);
+    /// assert_eq!(filter.peek_oldest(), Some(1));
+    /// assert_eq!(filter.median(), 2);
+    /// ```
+    pub fn from_slice(values: &[T]) -> Self {
+        let mut filter = Self::new();
+        filter.add_values(values);
+        filter
+    }
+
+    /// The values in the buffer from the oldest to the newest as an array, together with their
+    /// number, which is [`len`](Self::len). The values fill the start of the array and the rest is
+    /// zero, wherever in the buffer the window starts, so `&values[..len]` can be handed to code
+    /// that expects the samples in order. Use [`copy_window`](Self::copy_window) to copy into a
+    /// buffer of the caller instead.
+    ///
+    /// ```
+    /// use moving_median::MovingMedian;
+    ///
+    /// let mut filter = MovingMedian::<f32, 4>::new();
+    /// filter.add_values(&[1.0, 2.0, 3.0, 4.0, 5.0]);
+    ///
+    /// let (values, len) = filter.values();
+    /// assert_eq!(&values[..len], [2.0, 3.0, 4.0, 5.0]);
+    /// ```
+    pub fn values(&self) -> ([T; N], usize) {
+        let mut values = [T::zero(); N];
+        let len = self.copy_window(&mut values);
+        (values, len)
+    }
+
+    /// Remove the values for which `keep` returns `false` from the buffer, such as a sentinel that
+    /// means "no reading", without clearing the whole window. The remaining values keep their order
+    /// and are moved to the start of the buffer, and the slots they leave are filled with zero.
+    ///
+    /// ```
+    /// use moving_median::MovingMedian;
+    ///
+    /// let mut filter = MovingMedian::<u16, 4>::new();
+    /// filter.add_values(&[12, 0xFFFF, 10, 0xFFFF]);
+    /// filter.retain(|&value| value != 0xFFFF);
+    ///
+    /// assert_eq!(filter.len(), 2);
+    /// assert_eq!(filter.median(), 11);
+    /// ```
+    pub fn retain<F: Fn(&T) -> bool>(&mut self, keep: F) {
+        let old = self.buffer;
+        let mut kept = 0;
+        for i in 0..self.count {
+            let value = old[self.slot(i)];
+            if keep(&value) {
+                self.buffer[kept] = value;
+                kept += 1;
+            }
+        }
+        self.buffer[kept..].fill(T::zero());
+        self.index = kept % self.effective_len;
+        self.count = kept;
+        self.sorted_len = 0;
+        self.sum = None;
+        for i in 0..kept {
+            self.insert_sorted(self.buffer[i]);
+        }
+    }
+
+    /// Calculate the median of the values in the buffer.
+    /// The median is the middle value when the values are sorted in ascending order.
+    /// If the number of values is even, the median is the average of the two middle values,
+    /// rounded towards negative infinity for integers (see [`Sample::midpoint`]), or one of the
+    /// two middle values if another [`EvenStrategy`] is set.
+    /// If the number of values is odd, the median is the middle value.
+    /// If no values have been added, zero is returned, or the default set with
+    /// [`clear_with_default`](Self::clear_with_default). Use [`try_median`](Self::try_median)
+    /// to tell an empty filter apart from a median of zero.
+    ///
+    /// Nothing is sorted here: [`add_value`](Self::add_value) keeps the sorted buffer up to date,
+    /// so the median is a lookup of the middle values and calling it several times per added value
+    /// needs no caching. Only [`NanPolicy::Propagate`] scans the window for NaN values.
+    pub fn median(&self) -> T {
+        self.try_median().or(self.empty_median).unwrap_or(T::zero())
+    }
+
+    /// Calculate the median of the values in the buffer like [`median`](Self::median), without
+    /// checking whether there is one, for hot loops that already know the filter is not empty,
+    /// for example from [`is_full`](Self::is_full).
+    ///
+    /// The caller must make sure that the buffer holds at least one value other than NaN, or a NaN
+    /// under [`NanPolicy::Propagate`]. Otherwise the result is a meaningless value rather than
+    /// zero or the default of [`clear_with_default`](Self::clear_with_default). This is never
+    /// undefined behavior, and debug builds panic instead.
+    ///
+    /// ```
+    /// use moving_median::MovingMedian;
+    ///
+    /// let filter = MovingMedian::<i32, 4>::from([8, 2, 6, 4]);
+    /// if filter.is_full() {
+    ///     assert_eq!(filter.median_unchecked(), 5);
+    /// }
+    /// ```
+    pub fn median_unchecked(&self) -> T {
+        if let Some(nan) = self.propagated_nan() {
+            return nan;
+        }
+        let len = self.sorted_len;
+        debug_assert!(len > 0, "median_unchecked() of a filter without a median");
+        // Both indices stay in bounds for an empty buffer, which yields the stale first slot
+        let lower = self.sorted[len.saturating_sub(1) / 2];
+        let upper = self.sorted[len / 2];
+        match self.even_strategy {
+            EvenStrategy::AverageMiddle if len.is_multiple_of(2) => lower.midpoint(upper),
+            EvenStrategy::TakeLower => lower,
+            _ => upper,
+        }
+    }
+
+    /// Calculate the median of the values in the buffer, like [`median`](Self::median).
+    /// Returns `None` if no values have been added, or if the buffer only holds NaN values
+    /// under [`NanPolicy::Ignore`].
+    pub fn try_median(&self) -> Option<T> {
+        if let Some(nan) = self.propagated_nan() {
+            return Some(nan);
+        }
+        self.median_of_ordered()
+    }
+
+    /// Calculate the median like [`try_median`](Self::try_median), but only once the buffer is
+    /// [full](Self::is_full), for applications that would rather have no output than a jumpy
+    /// median of the first few values. Returns `None` while the buffer is warming up, which
+    /// starts over after [`clear`](Self::clear) and [`set_window`](Self::set_window).
+    ///
+    /// ```
+    /// use moving_median::MovingMedian;
+    ///
+    /// let mut filter = MovingMedian::<f32, 3>::new();
+    /// filter.add_value(7.0);
+    /// filter.add_value(3.0);
+    /// assert_eq!(filter.median_full_only(), None);
+    ///
+    /// filter.add_value(5.0);
+    /// assert_eq!(filter.median_full_only(), Some(5.0));
+    /// ```
+    pub fn median_full_only(&self) -> Option<T> {
+        if !self.is_full() {
+            return None;
+        }
+        self.try_median()
+    }
+
+    /// Calculate the median of the values in the buffer that are not NaN, whatever the [`NanPolicy`].
+    /// [`median`](Self::median) and [`try_median`](Self::try_median) do the same under the default
+    /// [`NanPolicy::Ignore`], but report NaN under [`NanPolicy::Propagate`].
+    /// Returns `None` if the buffer holds no values other than NaN.
+    pub fn median_ignore_nan(&self) -> Option<T> {
+        self.median_of_ordered()
+    }
+
+    /// Calculate the median, rounding the average of the two middle values down.
+    /// This is the same as [`try_median`](Self::try_median), which already rounds integers towards negative infinity.
+    pub fn median_floor(&self) -> Option<T> {
+        self.median_with(T::midpoint)
+    }
+
+    /// Calculate the median, rounding the average of the two middle values up.
+    /// For integers the result is rounded towards positive infinity (see [`Sample::midpoint_ceil`]).
+    pub fn median_ceil(&self) -> Option<T> {
+        self.median_with(T::midpoint_ceil)
+    }
+
+    /// Calculate a weighted median of the values in the buffer where newer values count more, for
+    /// tracking slowly drifting signals. The newest value has a weight of 1 and every older value
+    /// `lambda` times the weight of the next newer one, so a value `age` steps old weighs `lambda^age`.
+    /// The median is the value at which the cumulative weight, in ascending order of the values,
+    /// reaches half of the total weight, like for [`WeightedMovingMedian`].
+    ///
+    /// `lambda` is between 0 and 1: a `lambda` of 1 gives the plain median, and the smaller it is,
+    /// the closer the median follows the newest value. NaN values are handled according to the
+    /// [`NanPolicy`]. Returns `None` if the buffer holds no values other than NaN.
+    ///
+    /// ```
+    /// use moving_median::MovingMedian;
+    ///
+    /// let mut filter = MovingMedian::<f32, 5>::new();
+    /// filter.add_values(&[1.0, 2.0, 3.0, 10.0, 11.0]);
+    ///
+    /// assert_eq!(filter.decayed_median(1.0), Some(3.0));
+    /// assert_eq!(filter.decayed_median(0.8), Some(10.0));
+    /// assert_eq!(filter.decayed_median(0.5), Some(11.0));
+    /// ```
+    pub fn decayed_median(&self, lambda: f64) -> Option<T> {
+        if let Some(nan) = self.propagated_nan() {
+            return Some(nan);
+        }
+        // Collect the values with their weights, from the newest to the oldest
+        let mut pairs = [(T::zero(), 0.0); N];
+        let mut len = 0;
+        let mut weight = 1.0;
+        for &value in self.iter().rev() {
+            if !value.is_nan() {
+                pairs[len] = (value, weight);
+                len += 1;
+            }
+            weight *= lambda;
+        }
+        let pairs = &mut pairs[..len];
+        insertion_sort_by(pairs, |a, b| a.0 < b.0);
+
+        let total = pairs.iter().map(|pair| pair.1).sum();
+        weighted_median_of(pairs, total)
+    }
+
+    /// Calculate the median of the values in the buffer in the order given by `compare`, for types
+    /// with a domain-specific order or to reverse the order. The window is copied and sorted with
+    /// `compare` on every call, rather than read from the sorted buffer.
+    /// All values take part, including NaN, so `compare` decides where they go. The two middle
+    /// values of an even number of values are still averaged with [`Sample::midpoint`], which
+    /// assumes that values in between them in `compare` order are also in between them numerically.
+    /// Returns `None` if no values have been added.
+    pub fn median_by<F: Fn(&T, &T) -> core::cmp::Ordering>(&self, compare: F) -> Option<T> {
+        let mut values = [T::zero(); N];
+        for (slot, &value) in values.iter_mut().zip(self.iter()) {
+            *slot = value;
+        }
+        let values = &mut values[..self.count];
+        values.sort_unstable_by(compare);
+        middle(values, T::midpoint)
+    }
+
+    /// The spread of the buffer, the largest value minus the smallest, or `None` if no values have been added.
+    /// For signed integers a range that does not fit into the type saturates at its maximum.
+    /// NaN values are handled according to the [`NanPolicy`], like for [`min`](Self::min) and [`max`](Self::max).
+    pub fn range(&self) -> Option<T> {
+        Some(self.max()?.abs_diff(self.min()?))
+    }
+
+    /// The interquartile range of the buffer, the [`quantile`](Self::quantile) 0.75 minus the
+    /// quantile 0.25 with [`Interpolation::Linear`], which interpolates like the average of the two
+    /// middle values of the median. For integers both quartiles are rounded towards negative
+    /// infinity before the difference is taken. A single value has a range of zero.
+    /// Returns `None` if no values have been added.
+    /// NaN values are handled according to the [`NanPolicy`], like for the median.
+    ///
+    /// ```
+    /// use moving_median::MovingMedian;
+    ///
+    /// let filter = MovingMedian::<f32, 4>::from([4.0, 1.0, 3.0, 2.0]);
+    /// assert_eq!(filter.iqr(), Some(1.5));
+    /// ```
+    pub fn iqr(&self) -> Option<T> {
+        let lower = self.quantile(0.25, Interpolation::Linear)?;
+        let upper = self.quantile(0.75, Interpolation::Linear)?;
+        Some(upper.abs_diff(lower))
+    }
+
+    /// Calculate the quantile `q` of the values in the buffer, where `q` is between 0 and 1.
+    /// The values are taken in ascending order and the quantile is found at rank `q * (len - 1)`,
+    /// so a `q` of 0 is the minimum, 1 the maximum and 0.5 the median.
+    /// Returns `None` if no values have been added or `q` is outside of 0 to 1.
+    /// NaN values are handled according to the [`NanPolicy`], like for the median.
+    ///
+    /// ```
+    /// use moving_median::{Interpolation, MovingMedian};
+    ///
+    /// let mut filter = MovingMedian::<f32, 5>::new();
+    /// for value in [10.0, 20.0, 30.0, 40.0, 50.0] {
+    ///     filter.add_value(value);
+    /// }
+    /// assert_eq!(filter.quantile(0.1, Interpolation::Nearest), Some(10.0));
+    /// assert_eq!(filter.quantile(0.1, Interpolation::Linear), Some(14.0));
+    /// ```
+    pub fn quantile(&self, q: f64, interpolation: Interpolation) -> Option<T> {
+        if !(0.0..=1.0).contains(&q) {
+            return None;
+        }
+        if let Some(nan) = self.propagated_nan() {
+            return Some(nan);
+        }
+        let sorted = &self.sorted[..self.sorted_len];
+        let rank = q * (sorted.len().checked_sub(1)? as f64);
+        match interpolation {
+            Interpolation::Nearest => Some(sorted[(rank + 0.5) as usize]),
+            Interpolation::Linear => {
+                let below = rank as usize;
+                match sorted.get(below + 1) {
+                    Some(&above) => Some(sorted[below].lerp(above, rank - below as f64)),
+                    None => Some(sorted[below]),
+                }
+            }
+        }
+    }
+
+    /// Calculate the median absolute deviation of the values in the buffer, which is the median of
+    /// the absolute differences between each value and the median. Unlike the standard deviation
+    /// it stays small when a few values are outliers.
+    /// Returns `None` if no values have been added.
+    /// NaN values are handled according to the [`NanPolicy`], like for the median.
+    pub fn mad(&self) -> Option<T> {
+        if let Some(nan) = self.propagated_nan() {
+            return Some(nan);
+        }
+        let sorted = &self.sorted[..self.sorted_len];
+        let median = middle(sorted, T::midpoint)?;
+        // Walk outwards from the median, which yields the deviations in ascending order
+        let mut deviations = [T::zero(); N];
+        let mut below = lower_bound(sorted, median);
+        let mut above = below;
+        for deviation in deviations[..sorted.len()].iter_mut() {
+            let take_below = match (below.checked_sub(1), sorted.get(above)) {
+                (Some(next_below), Some(&next_above)) => {
+                    median.abs_diff(sorted[next_below]) <= next_above.abs_diff(median)
+                }
+                (below, _) => below.is_some(),
+            };
+            *deviation = if take_below {
+                below -= 1;
+                median.abs_diff(sorted[below])
+            } else {
+                above += 1;
+                sorted[above - 1].abs_diff(median)
+            };
+        }
+        middle(&deviations[..sorted.len()], T::midpoint)
+    }
+
+    /// Returns `true` if `value` differs from the median of the buffer by more than `k` times the
+    /// [`mad`](Self::mad), the outlier test of a Hampel filter (see [`HampelFilter`]).
+    /// The comparison is done in double precision. Returns `false` if no values have been added.
+    pub fn is_outlier(&self, value: T, k: T) -> bool {
+        match (self.try_median(), self.mad()) {
+            (Some(median), Some(mad)) => {
+                value.abs_diff(median).to_f64() > k.to_f64() * mad.to_f64()
+            }
+            _ => false,
+        }
+    }
+
+    /// Returns `true` if `value` lies outside of the Tukey fences of the buffer, below the quartile
+    /// 0.25 minus `k` times the [`iqr`](Self::iqr) or above the quartile 0.75 plus `k` times it.
+    /// A `k` of 1.5 is the usual choice and 3 flags only far outliers. The comparison is done in
+    /// double precision. A window of identical values has fences at that value, so every other
+    /// value is an outlier. Returns `false` while fewer than 4 values other than NaN have been added,
+    /// which are too few to judge, and for a NaN `value`.
+    ///
+    /// Unlike [`is_outlier`](Self::is_outlier), which compares with the [`mad`](Self::mad), the
+    /// fences need not be symmetric around the median.
+    ///
+    /// ```
+    /// use moving_median::MovingMedian;
+    ///
+    /// let filter = MovingMedian::<i32, 5>::from([10, 12, 11, 13, 12]);
+    /// assert!(!filter.is_iqr_outlier(13, 1));
+    /// assert!(filter.is_iqr_outlier(40, 1));
+    /// ```
+    pub fn is_iqr_outlier(&self, value: T, k: T) -> bool {
+        if self.sorted_len < 4 {
+            return false;
+        }
+        match (
+            self.quantile(0.25, Interpolation::Linear),
+            self.quantile(0.75, Interpolation::Linear),
+        ) {
+            (Some(lower), Some(upper)) => {
+                let fence = k.to_f64() * upper.abs_diff(lower).to_f64();
+                let value = value.to_f64();
+                value < lower.to_f64() - fence || value > upper.to_f64() + fence
+            }
+            _ => false,
+        }
+    }
+
+    /// Calculate the percentile `p` of the values in the buffer, where `p` is clamped to 0 to 100.
+    /// Equivalent to [`quantile`](Self::quantile) of `p / 100` with [`Interpolation::Linear`],
+    /// so the 50th percentile is the median.
+    /// Returns `None` if no values have been added or `p` is NaN.
+    pub fn percentile(&self, p: f64) -> Option<T> {
+        self.quantile(p.clamp(0.0, 100.0) / 100.0, Interpolation::Linear)
+    }
+
+    /// Calculate the arithmetic mean of the values in the buffer.
+    /// For integers the sum is taken in a wider type so it cannot overflow, and the mean is
+    /// rounded towards negative infinity (see [`Sample::mean`]).
+    /// NaN values are handled according to the [`NanPolicy`], like for the median.
+    /// The sum is kept up to date as values are added and removed, so the mean takes `O(1)`, and it
+    /// is added up again from the window every time the buffer wraps around, so rounding errors
+    /// of floats cannot build up.
+    /// If no values have been added, zero is returned.
+    pub fn mean(&self) -> T {
+        self.try_mean().unwrap_or(T::zero())
+    }
+
+    /// Calculate the arithmetic mean of the values in the buffer, like [`mean`](Self::mean).
+    /// Returns `None` if the buffer holds no values other than NaN.
+    pub fn try_mean(&self) -> Option<T> {
+        if let Some(nan) = self.propagated_nan() {
+            return Some(nan);
+        }
+        Some(T::mean(self.sum?, self.sorted_len))
+    }
+
+    /// Calculate the median and the mean of the values in the buffer, in that order, as
+    /// [`try_median`](Self::try_median) and [`mean`](Self::mean) do. Both are read from the sorted
+    /// buffer, so asking for them together does not sort or scan the window twice.
+    /// Returns `None` if the buffer holds no values other than NaN.
+    ///
+    /// ```
+    /// use moving_median::MovingMedian;
+    ///
+    /// let mut filter = MovingMedian::<f32, 4>::new();
+    /// filter.add_values(&[1.0, 2.0, 3.0, 10.0]);
+    ///
+    /// assert_eq!(filter.median_and_mean(), Some((2.5, 4.0)));
+    /// ```
+    pub fn median_and_mean(&self) -> Option<(T, T)> {
+        if let Some(nan) = self.propagated_nan() {
+            return Some((nan, nan));
+        }
+        let median = self.median_of_ordered()?;
+        Some((median, self.mean()))
+    }
+
+    /// Calculate the trimmed mean of the values in the buffer, the arithmetic mean after leaving out
+    /// the `trim` smallest and the `trim` largest values. The sum is taken like for [`mean`](Self::mean),
+    /// so it cannot overflow for integers, and the mean is rounded towards negative infinity.
+    /// Returns `None` if no values remain, that is if `2 * trim` is not less than the number of values.
+    /// NaN values are handled according to the [`NanPolicy`], like for the median.
+    pub fn trimmed_mean(&self, trim: usize) -> Option<T> {
+        if let Some(nan) = self.propagated_nan() {
+            return Some(nan);
+        }
+        let values =
+            self.sorted[..self.sorted_len].get(trim..self.sorted_len.checked_sub(trim)?)?;
+        if values.is_empty() {
+            return None;
+        }
+        let sum = values.iter().fold(T::zero().accumulate(), |sum, value| {
+            sum + value.accumulate()
+        });
+        Some(T::mean(sum, values.len()))
+    }
+
+    /// Calculate the trimmed mean of the values in the buffer, leaving out the fraction `trim_fraction`
+    /// of the values at each end, like [`trimmed_mean`](Self::trimmed_mean) with
+    /// `floor(len * trim_fraction)` values trimmed. The trim is limited so at least one value remains,
+    /// which makes a fraction of 0.5 or more the median of an odd number of values, and a negative
+    /// fraction the plain mean. Returns `None` if no values other than NaN have been added.
+    ///
+    /// ```
+    /// use moving_median::MovingMedian;
+    ///
+    /// let filter = MovingMedian::<i32, 5>::from([1, 2, 3, 4, 100]);
+    /// assert_eq!(filter.trimmed_mean_fraction(0.25), Some(3));
+    /// ```
+    pub fn trimmed_mean_fraction(&self, trim_fraction: f64) -> Option<T> {
+        let len = self.sorted_len;
+        let trim = (len as f64 * trim_fraction.clamp(0.0, 0.5)) as usize;
+        self.trimmed_mean(trim.min(len.saturating_sub(1) / 2))
+    }
+
+    /// Calculate the population variance of the values in the buffer, the mean of the squared
+    /// differences from the mean. The calculation is done in double precision, and for integers the
+    /// result is rounded towards negative infinity (see [`Sample::from_f64`]).
+    /// Returns `None` if no values have been added.
+    /// NaN values are handled according to the [`NanPolicy`], like for the median.
+    pub fn variance(&self) -> Option<T> {
+        self.variance_f64().map(T::from_f64)
+    }
+
+    /// Calculate the population standard deviation of the values in the buffer, the square root of
+    /// the [`variance`](Self::variance). Requires the `libm` feature for the square root.
+    /// Returns `None` if no values have been added.
+    #[cfg(feature = "libm")]
+    pub fn std_dev(&self) -> Option<T> {
+        self.variance_f64()
+            .map(|variance| T::from_f64(libm::sqrt(variance)))
+    }
+
+    // The population variance in double precision, so the standard deviation is taken before rounding
+    fn variance_f64(&self) -> Option<f64> {
+        if let Some(nan) = self.propagated_nan() {
+            return Some(nan.to_f64());
+        }
+        let values = &self.sorted[..self.sorted_len];
+        if values.is_empty() {
+            return None;
+        }
+        let len = values.len() as f64;
+        let mean = values.iter().map(|value| value.to_f64()).sum::<f64>() / len;
+        let squares = values
+            .iter()
+            .map(|value| {
+                let difference = value.to_f64() - mean;
+                difference * difference
+            })
+            .sum::<f64>();
+        Some(squares / len)
+    }
+
+    // The median of the values other than NaN, according to the even strategy
+    fn median_of_ordered(&self) -> Option<T> {
+        let sorted = &self.sorted[..self.sorted_len];
+        match self.even_strategy {
+            EvenStrategy::AverageMiddle => middle(sorted, T::midpoint),
+            EvenStrategy::TakeLower => sorted.get(sorted.len().checked_sub(1)? / 2).copied(),
+            EvenStrategy::TakeUpper => sorted.get(sorted.len() / 2).copied(),
+        }
+    }
+
+    // Calculate the median using `average` for the two middle values of an even number of values
+    fn median_with(&self, average: fn(T, T) -> T) -> Option<T> {
+        if let Some(nan) = self.propagated_nan() {
+            return Some(nan);
+        }
+        middle(&self.sorted[..self.sorted_len], average)
+    }
+}
+
+// Everything that only needs the values to be ordered, so it also works for types without arithmetic,
+// which implement `WindowSum` without a sum
+impl<T: WindowSum, const N: usize> MovingMedian<T, N> {
+    /// Create a new, empty moving median filter whose unused buffer slots are filled with `value`.
+    /// Unlike [`new`](Self::new) this is a `const fn`, so the filter can be created in a `static` or `const`.
+    ///
+    /// A filter shared with an interrupt handler can be put into a `static` behind a lock, such as a
+    /// `critical_section::Mutex` in firmware or a `std::sync::Mutex` on a host:
+    ///
+    /// ```
+    /// use core::cell::RefCell;
+    /// use moving_median::MovingMedian;
+    /// use std::sync::Mutex;
+    ///
+    /// static FILTER: Mutex<RefCell<MovingMedian<i32, 15>>> =
+    ///     Mutex::new(RefCell::new(MovingMedian::with_default(0)));
+    /// const EMPTY: MovingMedian<i32, 4> = MovingMedian::with_default(0);
+    ///
+    /// FILTER.lock().unwrap().borrow_mut().add_values(&[3, 1, 2]);
+    /// assert_eq!(FILTER.lock().unwrap().borrow().median(), 2);
+    /// assert!(EMPTY.is_empty());
+    /// ```
+    #[doc(alias = "new_with")]
+    pub const fn with_default(value: T) -> Self {
+        const { assert!(N > 0, "a MovingMedian needs a buffer of at least one value") };
         Self {
-            buffer: [T::default(); N],
+            buffer: [value; N],
             index: 0,
             count: 0,
+            effective_len: N,
+            sorted: [value; N],
+            sorted_len: 0,
+            nan_policy: NanPolicy::Ignore,
+            even_strategy: EvenStrategy::AverageMiddle,
+            total: 0,
+            empty_median: None,
+            sum: None,
         }
     }
 
     /// Add a new measurement to the buffer.
     /// If the buffer is full, the oldest value will be replaced.
-    /// The buffer will always contain the last N measurements.
+    /// The buffer will always contain the last N measurements, or the last
+    /// [`effective_len`](Self::effective_len) measurements if the window was shortened.
     /// The count will be incremented up to N.
-    pub fn add_value(&mut self, value: T) {
+    /// Returns the replaced oldest value, or `None` while the buffer is still filling up.
+    pub fn add_value(&mut self, value: T) -> Option<T> {
+        // Remove the value that is about to be overwritten from the sorted buffer
+        let evicted = if self.count == self.effective_len {
+            let evicted = self.buffer[self.index];
+            self.remove_sorted(evicted);
+            self.count -= 1;
+            Some(evicted)
+        } else {
+            None
+        };
+        self.insert_sorted(value);
         // Add the new value to the buffer
         self.buffer[self.index] = value;
         // Move to the next index, wrapping around if necessary
-        self.index = (self.index + 1) % N;
-        // Increment the count up to N
-        if self.count < N {
-            self.count += 1;
+        self.index = (self.index + 1) % self.effective_len;
+        if self.index == 0 {
+            self.resum();
         }
+        // Increment the count up to the effective length
+        self.count += 1;
+        self.total = self.total.wrapping_add(1);
+        evicted
     }
 
-    /// Calculate the median of the values in the buffer.
-    /// The median is the middle value when the values are sorted in ascending order.
-    /// If the number of values is even, the median is the average of the two middle values.
-    /// If the number of values is odd, the median is the middle value.
-    pub fn median(&self) -> T {
-        // If no values have been added, return 0.0
-        if self.count == 0 {
-            return T::from(0);
+    /// Add several measurements to the buffer, in order, as if [`add_value`](Self::add_value) was called for each.
+    /// If at least N values are given, only the last N remain in the buffer. Everything else would
+    /// be evicted anyway, so the last N values are written straight into the buffer and sorted once.
+    pub fn add_values(&mut self, values: &[T]) {
+        let len = self.effective_len;
+        if values.is_empty() || values.len() < len {
+            for &value in values {
+                self.add_value(value);
+            }
+            return;
         }
-
-        // Create a copy of the buffer and sort it using bubble sort
-        let mut sorted_buffer = self.buffer;
-        for i in 0..self.count {
-            for j in 0..self.count - i - 1 {
-                if sorted_buffer[j] > sorted_buffer[j + 1] {
-                    sorted_buffer.swap(j, j + 1);
-                }
+        // Lay the values out with the same index as adding them one by one would leave
+        self.index = (self.index + values.len()) % len;
+        self.total = self.total.wrapping_add(values.len() as u64);
+        for (i, &value) in values[values.len() - len..].iter().enumerate() {
+            self.buffer[(self.index + i) % len] = value;
+        }
+        self.count = len;
+        self.sorted_len = 0;
+        for &value in &self.buffer[..len] {
+            if !unordered(&value) {
+                self.sorted[self.sorted_len] = value;
+                self.sorted_len += 1;
             }
         }
+        self.sorted[..self.sorted_len].sort_unstable_by(|a, b| a.partial_cmp(b).unwrap());
+        self.resum();
+    }
 
-        // Find the median
-        if self.count % 2 == 0 {
-            // Even number of elements, take the average of the two middle elements
-            (sorted_buffer[self.count / 2 - 1] + sorted_buffer[self.count / 2]) / T::from(2)
-        } else {
-            // Odd number of elements, take the middle element
-            sorted_buffer[self.count / 2]
+    /// Remove the oldest value from the buffer without adding a new one, and return it.
+    /// Popping a value every missed sample period lets the window age out during a dropout,
+    /// until the filter is empty and the median is `None` again.
+    /// Returns `None` if no values have been added.
+    ///
+    /// ```
+    /// use moving_median::MovingMedian;
+    ///
+    /// let mut filter = MovingMedian::<f32, 3>::new();
+    /// filter.add_values(&[1.0, 2.0, 9.0]);
+    ///
+    /// assert_eq!(filter.pop_oldest(), Some(1.0));
+    /// assert_eq!(filter.median(), 5.5);
+    /// filter.pop_oldest();
+    /// filter.pop_oldest();
+    /// assert_eq!(filter.try_median(), None);
+    /// ```
+    pub fn pop_oldest(&mut self) -> Option<T> {
+        let oldest = self.peek_oldest()?;
+        self.remove_sorted(oldest);
+        // The index of the next value stays, so the oldest slot is simply no longer counted
+        self.count -= 1;
+        Some(oldest)
+    }
+
+    /// Overwrite the most recently added value with `value`, and return the value it replaced.
+    /// Use this to correct a measurement that turned out to be wrong after it was added.
+    /// Returns `None` and leaves the filter unchanged if no values have been added.
+    pub fn replace_last(&mut self, value: T) -> Option<T> {
+        let newest = self.peek_newest()?;
+        let slot = self.slot(self.count - 1);
+        self.remove_sorted(newest);
+        self.insert_sorted(value);
+        self.buffer[slot] = value;
+        Some(newest)
+    }
+
+    /// Remove the most recently added value from the buffer, and return it.
+    /// A value that adding it pushed out of a full buffer is not brought back.
+    /// Returns `None` and leaves the filter unchanged if no values have been added.
+    ///
+    /// ```
+    /// use moving_median::MovingMedian;
+    ///
+    /// let mut filter = MovingMedian::<i32, 3>::new();
+    /// filter.add_values(&[10, 12, 4000]);
+    ///
+    /// assert_eq!(filter.undo_last(), Some(4000));
+    /// assert_eq!(filter.median(), 11);
+    /// assert_eq!(filter.replace_last(14), Some(12));
+    /// assert_eq!(filter.median(), 12);
+    /// ```
+    pub fn undo_last(&mut self) -> Option<T> {
+        let newest = self.peek_newest()?;
+        self.remove_sorted(newest);
+        // Step the index back onto the slot of the removed value, so it is the next one written
+        self.index = (self.index + self.effective_len - 1) % self.effective_len;
+        self.count -= 1;
+        Some(newest)
+    }
+
+    /// Remove all values from the buffer, so the filter starts over as if it was new.
+    pub fn clear(&mut self) {
+        self.index = 0;
+        self.count = 0;
+        self.sorted_len = 0;
+        self.sum = None;
+        self.total = 0;
+        self.empty_median = None;
+    }
+
+    /// Remove all values from the buffer, like [`clear`](Self::clear), and make [`median`](Self::median)
+    /// return `default` instead of zero until values are added again, such as a known baseline.
+    /// The default is also returned when the buffer holds nothing but NaN values. Calling
+    /// [`clear`](Self::clear) makes the empty median zero again.
+    ///
+    /// ```
+    /// use moving_median::MovingMedian;
+    ///
+    /// let mut filter = MovingMedian::<f32, 3>::new();
+    /// filter.clear_with_default(20.0);
+    /// assert_eq!(filter.median(), 20.0);
+    /// assert_eq!(filter.try_median(), None);
+    ///
+    /// filter.add_value(22.5);
+    /// assert_eq!(filter.median(), 22.5);
+    /// ```
+    pub fn clear_with_default(&mut self, default: T) {
+        self.clear();
+        self.empty_median = Some(default);
+    }
+
+    /// Fill the buffer with `value`, so the filter is full and the median is `value`.
+    /// Unlike [`clear`](Self::clear), which empties the filter, this warm-starts it to a known value.
+    pub fn reset_to(&mut self, value: T) {
+        self.buffer = [value; N];
+        self.index = 0;
+        self.count = self.effective_len;
+        self.sorted = [value; N];
+        self.sorted_len = if unordered(&value) { 0 } else { self.count };
+        self.resum();
+    }
+
+    /// The state of the filter as `(buffer, index, count)`, for saving it without serde, such as
+    /// into battery-backed RAM across a reset. [`from_parts`](Self::from_parts) restores it.
+    /// The NaN policy and even strategy are not included. If the window was shortened with
+    /// [`set_window`](Self::set_window), the values are laid out from the start of the buffer, so
+    /// calling `set_window` again after `from_parts` restores the filter.
+    ///
+    /// ```
+    /// use moving_median::MovingMedian;
+    ///
+    /// let mut filter = MovingMedian::<u16, 4>::new();
+    /// filter.add_values(&[1, 2, 3, 4, 5]);
+    /// let (buffer, index, count) = filter.to_parts();
+    /// assert_eq!((buffer, index, count), ([5, 2, 3, 4], 1, 4));
+    ///
+    /// let restored = MovingMedian::from_parts(buffer, index, count).unwrap();
+    /// assert_eq!(restored.median(), filter.median());
+    /// ```
+    pub fn to_parts(&self) -> ([T; N], usize, usize) {
+        if self.effective_len == N {
+            return (self.buffer, self.index, self.count);
         }
+        let mut buffer = self.buffer;
+        for (i, &value) in self.iter().enumerate() {
+            buffer[i] = value;
+        }
+        (buffer, self.count % N, self.count)
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// The complete state of the filter as a [`Snapshot`] with named fields, which
+    /// [`Snapshot::into_filter`] turns back into a filter. Unlike [`to_parts`](Self::to_parts) it
+    /// keeps the window length, the policies, the number of values added and the empty default.
+    ///
+    /// ```
+    /// use moving_median::{EvenStrategy, MovingMedian};
+    ///
+    /// let mut filter = MovingMedian::<i32, 4>::new();
+    /// filter.set_even_strategy(EvenStrategy::TakeUpper);
+    /// filter.set_window(3);
+    /// filter.add_values(&[4, 9, 1, 7]);
+    ///
+    /// let snapshot = filter.snapshot();
+    /// assert_eq!((snapshot.count, snapshot.index, snapshot.effective_len), (3, 1, 3));
+    /// let mut restored = snapshot.into_filter().unwrap();
+    /// assert_eq!(restored.add_value(2), filter.add_value(2));
+    /// assert_eq!(restored.median(), filter.median());
+    /// ```
+    pub fn snapshot(&self) -> Snapshot<T, N> {
+        Snapshot {
+            buffer: self.buffer,
+            count: self.count,
+            index: self.index,
+            effective_len: self.effective_len,
+            nan_policy: self.nan_policy,
+            even_strategy: self.even_strategy,
+            total: self.total,
+            empty_median: self.empty_median,
+        }
+    }
 
-    #[test]
-    fn median_is_zero_when_no_values_added() {
-        let filter = MovingMedian::<f64, 3>::new();
-        assert_eq!(filter.median(), 0.0);
+    /// Restore a filter from the parts returned by [`to_parts`](Self::to_parts).
+    /// The `count` values before `index`, wrapping around from the end of the buffer, are the
+    /// values in the window, and the sorted buffer is rebuilt from them.
+    /// Returns an error if `index` is not less than N or `count` is greater than N, rather than
+    /// building a filter that would index out of bounds later.
+    pub fn from_parts(buffer: [T; N], index: usize, count: usize) -> Result<Self, InvalidState> {
+        Snapshot {
+            buffer,
+            count,
+            index,
+            effective_len: N,
+            nan_policy: NanPolicy::default(),
+            even_strategy: EvenStrategy::default(),
+            total: 0,
+            empty_median: None,
+        }
+        .into_filter()
     }
 
-    #[test]
-    fn median_is_value_when_one_value_added() {
-        let mut filter = MovingMedian::<f64, 3>::new();
-        filter.add_value(42.0);
-        assert_eq!(filter.median(), 42.0);
+    /// Shorten or lengthen the window to the most recent `len` values, clamped to 1 to N, without
+    /// changing the size N of the buffer. A shorter window makes the filter respond faster.
+    /// Values older than the new window are dropped, and lengthening the window again does not
+    /// bring them back, the window fills up with new values instead.
+    ///
+    /// ```
+    /// use moving_median::MovingMedian;
+    ///
+    /// let mut filter = MovingMedian::<f32, 8>::new();
+    /// filter.add_values(&[1.0, 2.0, 3.0, 4.0, 5.0, 20.0, 30.0, 10.0]);
+    /// filter.set_window(3);
+    ///
+    /// assert_eq!(filter.median(), 20.0);
+    /// assert!(filter.is_full());
+    /// ```
+    pub fn set_window(&mut self, len: usize) {
+        let len = len.clamp(1, N.max(1));
+        let kept = self.count.min(len);
+        // Move the kept values to the start of the buffer, from oldest to newest
+        let old = self.buffer;
+        for i in 0..kept {
+            self.buffer[i] = old[self.slot(self.count - kept + i)];
+        }
+        self.effective_len = len;
+        self.index = kept % len;
+        self.count = kept;
+        self.sorted_len = 0;
+        self.sum = None;
+        for i in 0..kept {
+            self.insert_sorted(self.buffer[i]);
+        }
     }
 
-    #[test]
-    fn median_is_average_of_two_values_when_two_values_added() {
-        let mut filter = MovingMedian::<f64, 2>::new();
-        filter.add_value(42.0);
-        filter.add_value(43.0);
-        assert_eq!(filter.median(), 42.5);
+    /// Number of values the window holds once it is full, N unless it was changed with
+    /// [`set_window`](Self::set_window).
+    pub fn effective_len(&self) -> usize {
+        self.effective_len
     }
 
-    #[test]
-    fn median_is_middle_value_when_three_values_added() {
-        let mut filter = MovingMedian::<f64, 3>::new();
-        filter.add_value(42.0);
-        filter.add_value(43.0);
-        filter.add_value(41.0);
-        assert_eq!(filter.median(), 42.0);
+    /// Position in the buffer that the next [`add_value`](Self::add_value) writes to, which is
+    /// the slot of the oldest value once the buffer is full. It advances by one for every added
+    /// value and wraps around to 0 after N, or after [`effective_len`](Self::effective_len).
+    pub fn position(&self) -> usize {
+        self.index
     }
 
-    #[test]
-    fn median_is_average_of_two_middle_values_when_four_values_added() {
-        let mut filter = MovingMedian::<f64, 4>::new();
-        filter.add_value(42.0);
-        filter.add_value(43.0);
-        filter.add_value(41.0);
-        filter.add_value(44.0);
-        assert_eq!(filter.median(), 42.5);
+    /// Number of values in the buffer, at most N. NaN values are included.
+    pub fn len(&self) -> usize {
+        self.count
     }
 
-    #[test]
-    fn median_is_midlle_value_of_n_values_when_more_than_n_values_added() {
-        let mut filter = MovingMedian::<f64, 3>::new();
-        filter.add_value(42.0); // should be pushed out
-        filter.add_value(44.0);
-        filter.add_value(43.0); // should be the median
-        filter.add_value(41.0);
-        assert_eq!(filter.median(), 43.0);
+    /// Returns `true` if no values have been added.
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Number of values added since the filter was created or [cleared](Self::clear), including
+    /// the values that were evicted since, so it keeps counting once the buffer is full.
+    /// Removing values with [`pop_oldest`](Self::pop_oldest) or [`undo_last`](Self::undo_last)
+    /// does not change it. A filter restored with [`from_parts`](Self::from_parts) starts at zero,
+    /// while serde keeps the count.
+    ///
+    /// ```
+    /// use moving_median::MovingMedian;
+    ///
+    /// let mut filter = MovingMedian::<u8, 3>::new();
+    /// filter.add_values(&[1, 2, 3, 4, 5]);
+    ///
+    /// assert_eq!(filter.len(), 3);
+    /// assert_eq!(filter.total_pushed(), 5);
+    /// ```
+    pub fn total_pushed(&self) -> u64 {
+        self.total
+    }
+
+    /// Returns `true` once N values, or [`effective_len`](Self::effective_len) values, have been
+    /// added and the buffer has warmed up.
+    pub fn is_full(&self) -> bool {
+        self.count == self.effective_len
+    }
+
+    /// Returns `true` once the buffer has warmed up, the same as [`is_full`](Self::is_full).
+    /// The warm-up starts over after [`clear`](Self::clear).
+    pub fn is_warmed_up(&self) -> bool {
+        self.is_full()
+    }
+
+    /// Iterate over the values in the buffer, from the oldest to the newest.
+    /// The iterator knows its length and can be reversed to start with the newest value.
+    /// Iterating over `&filter` does the same.
+    pub fn iter(&self) -> WindowIter<'_, T, N> {
+        WindowIter {
+            filter: self,
+            front: 0,
+            back: self.count,
+        }
+    }
+
+    /// Copy the values in the buffer, from the oldest to the newest, into the start of `out` and
+    /// return the number of values copied, which is [`len`](Self::len). The buffer wraps around,
+    /// so this gives a contiguous slice for code that needs one, such as an FFI call.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `out` is shorter than [`len`](Self::len).
+    ///
+    /// ```
+    /// use moving_median::MovingMedian;
+    ///
+    /// let mut filter = MovingMedian::<u8, 3>::new();
+    /// filter.add_values(&[1, 2, 3, 4]);
+    ///
+    /// let mut out = [0; 3];
+    /// let len = filter.copy_window(&mut out);
+    /// assert_eq!(&out[..len], [2, 3, 4]);
+    /// ```
+    pub fn copy_window(&self, out: &mut [T]) -> usize {
+        assert!(
+            out.len() >= self.count,
+            "output of length {} cannot hold {} values",
+            out.len(),
+            self.count
+        );
+        for (slot, &value) in out.iter_mut().zip(self.iter()) {
+            *slot = value;
+        }
+        self.count
+    }
+
+    /// The most recently added value, or `None` if no values have been added.
+    pub fn peek_newest(&self) -> Option<T> {
+        self.iter().next_back().copied()
+    }
+
+    /// The oldest value in the buffer, which is the one the next [`add_value`](Self::add_value)
+    /// replaces once the buffer is full. Returns `None` if no values have been added.
+    pub fn peek_oldest(&self) -> Option<T> {
+        self.iter().next().copied()
+    }
+
+    /// The most recently added value, the same as [`peek_newest`](Self::peek_newest).
+    pub fn newest(&self) -> Option<T> {
+        self.peek_newest()
+    }
+
+    /// The oldest value in the buffer, the same as [`peek_oldest`](Self::peek_oldest).
+    pub fn oldest(&self) -> Option<T> {
+        self.peek_oldest()
+    }
+
+    /// Iterate over the values in the buffer in ascending order.
+    /// This reads the sorted buffer the median is taken from, so nothing is sorted on the way.
+    /// NaN values cannot be ordered and are not included.
+    pub fn iter_sorted(&self) -> impl DoubleEndedIterator<Item = T> + ExactSizeIterator + '_ {
+        self.sorted[..self.sorted_len].iter().copied()
+    }
+
+    /// Create a filter of another sample type by applying `f` to every value in the buffer, such as
+    /// converting raw ADC counts to volts. The window order, count, index, window length and policies
+    /// are kept, and the sorted buffer of the new filter is rebuilt, so `f` does not need to be monotonic.
+    /// `f` is also applied to buffer slots that do not hold a value yet.
+    ///
+    /// ```
+    /// use moving_median::MovingMedian;
+    ///
+    /// let mut counts = MovingMedian::<u16, 3>::new();
+    /// counts.add_values(&[1024, 4095, 2048]);
+    ///
+    /// let volts = counts.map(|count| count as f32 * 3.3 / 4095.0);
+    /// assert_eq!(volts.median(), 2048.0 * 3.3 / 4095.0);
+    /// ```
+    pub fn map<U: WindowSum, F: Fn(T) -> U>(&self, f: F) -> MovingMedian<U, N> {
+        let buffer = self.buffer.map(&f);
+        let mut mapped = MovingMedian {
+            buffer,
+            index: self.index,
+            count: self.count,
+            effective_len: self.effective_len,
+            sorted: buffer,
+            sorted_len: 0,
+            nan_policy: self.nan_policy,
+            even_strategy: self.even_strategy,
+            total: self.total,
+            empty_median: self.empty_median.map(&f),
+            sum: None,
+        };
+        for i in 0..mapped.count {
+            mapped.insert_sorted(mapped.buffer[mapped.slot(i)]);
+        }
+        mapped
+    }
+
+    /// Size N of the buffer, which is the number of values the filter holds once it is full.
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
+    /// The policy used for NaN values. Defaults to [`NanPolicy::Ignore`].
+    pub fn nan_policy(&self) -> NanPolicy {
+        self.nan_policy
+    }
+
+    /// Set the policy used for NaN values. Takes effect immediately, including for values already in the buffer.
+    pub fn set_nan_policy(&mut self, policy: NanPolicy) {
+        self.nan_policy = policy;
+    }
+
+    /// The strategy for the median of an even number of values. Defaults to [`EvenStrategy::AverageMiddle`].
+    pub fn even_strategy(&self) -> EvenStrategy {
+        self.even_strategy
+    }
+
+    /// Set the strategy for the median of an even number of values. Takes effect immediately.
+    /// [`median_floor`](Self::median_floor) and [`median_ceil`](Self::median_ceil) always average.
+    pub fn set_even_strategy(&mut self, strategy: EvenStrategy) {
+        self.even_strategy = strategy;
+    }
+
+    /// The smallest value in the buffer, or `None` if no values have been added.
+    /// This is a lookup in the sorted buffer, so it does not scan the window.
+    /// NaN values are handled according to the [`NanPolicy`], like for the median.
+    pub fn min(&self) -> Option<T> {
+        if let Some(nan) = self.propagated_nan() {
+            return Some(nan);
+        }
+        self.sorted[..self.sorted_len].first().copied()
+    }
+
+    /// The largest value in the buffer, or `None` if no values have been added.
+    /// This is a lookup in the sorted buffer, so it does not scan the window.
+    /// NaN values are handled according to the [`NanPolicy`], like for the median.
+    pub fn max(&self) -> Option<T> {
+        if let Some(nan) = self.propagated_nan() {
+            return Some(nan);
+        }
+        self.sorted[..self.sorted_len].last().copied()
+    }
+
+    /// The most frequent value in the buffer, or `None` if no values have been added.
+    /// If several values are equally frequent, the smallest of them is returned.
+    /// Equal values are next to each other in the sorted buffer, so this is a single pass over it.
+    /// NaN values are handled according to the [`NanPolicy`], like for the median.
+    pub fn mode(&self) -> Option<T> {
+        if let Some(nan) = self.propagated_nan() {
+            return Some(nan);
+        }
+        let sorted = &self.sorted[..self.sorted_len];
+        let mut mode = *sorted.first()?;
+        let mut mode_run = 0;
+        let mut run = 0;
+        for (i, &value) in sorted.iter().enumerate() {
+            run = if i > 0 && sorted[i - 1] == value {
+                run + 1
+            } else {
+                1
+            };
+            // Only a strictly longer run replaces the mode, so ties keep the smaller value
+            if run > mode_run {
+                mode = value;
+                mode_run = run;
+            }
+        }
+        Some(mode)
+    }
+
+    /// The middle value of the buffer when the values are ordered by the key `key`, for example the
+    /// reading with the median value in a buffer of timestamped readings. The whole value is returned,
+    /// and for an even number of values it is the lower of the two middle values. Unlike
+    /// [`median_by`](Self::median_by) values are never averaged, so this works for any ordered type.
+    /// Values whose key cannot be ordered, such as a NaN reading, are left out.
+    /// Returns `None` if no values with an ordered key have been added.
+    ///
+    /// ```
+    /// use moving_median::MovingMedian;
+    ///
+    /// // (timestamp, reading)
+    /// let mut filter = MovingMedian::<(u32, f32), 3>::with_default((0, 0.0));
+    /// filter.add_value((100, 21.5));
+    /// filter.add_value((101, 19.0));
+    /// filter.add_value((102, 20.5));
+    ///
+    /// assert_eq!(filter.median_by_key(|&(_, reading)| reading), Some((102, 20.5)));
+    /// ```
+    pub fn median_by_key<K: PartialOrd>(&self, key: impl Fn(&T) -> K) -> Option<T> {
+        // The window can start anywhere in the buffer, so walk it from the oldest value
+        let mut values = self.buffer;
+        let mut len = 0;
+        for &value in self.iter() {
+            if !unordered(&key(&value)) {
+                values[len] = value;
+                len += 1;
+            }
+        }
+        let values = &mut values[..len];
+        values.sort_unstable_by(|a, b| key(a).partial_cmp(&key(b)).unwrap());
+        values.get(len.checked_sub(1)? / 2).copied()
+    }
+
+    /// The lower of the two middle values for an even number of values, or the middle value for
+    /// an odd number. Unlike [`median`](Self::median) the values are never averaged, so this works
+    /// for types that can be ordered but not divided, such as timestamps.
+    /// Returns `None` if no values have been added.
+    /// NaN values are handled according to the [`NanPolicy`], like for the median.
+    pub fn median_low(&self) -> Option<T> {
+        if let Some(nan) = self.propagated_nan() {
+            return Some(nan);
+        }
+        let position = self.sorted_len.checked_sub(1)? / 2;
+        Some(self.sorted[position])
+    }
+
+    /// The upper of the two middle values for an even number of values, or the middle value for
+    /// an odd number, like [`median_low`](Self::median_low).
+    /// Returns `None` if no values have been added.
+    pub fn median_high(&self) -> Option<T> {
+        if let Some(nan) = self.propagated_nan() {
+            return Some(nan);
+        }
+        self.sorted[..self.sorted_len]
+            .get(self.sorted_len / 2)
+            .copied()
+    }
+
+    // A NaN value from the buffer, if there is one and the policy propagates it
+    fn propagated_nan(&self) -> Option<T> {
+        if self.nan_policy != NanPolicy::Propagate {
+            return None;
+        }
+        self.iter().copied().find(|value| unordered(value))
+    }
+
+    // Position in the buffer of the `i`-th oldest value
+    fn slot(&self, i: usize) -> usize {
+        (self.index + self.effective_len - self.count + i) % self.effective_len
+    }
+
+    // Insert a value into the sorted buffer at its position and add it to the sum, unless it
+    // cannot be ordered
+    fn insert_sorted(&mut self, value: T) {
+        let len = self.sorted_len;
+        self.sorted_len = insert_sorted(&mut self.sorted, len, value);
+        if self.sorted_len > len {
+            self.sum = Some(match self.sum {
+                Some(sum) => value.add_to_sum(sum),
+                None => value.to_sum(),
+            });
+        }
+    }
+
+    // Remove a value that is in the buffer from the sorted buffer and take it out of the sum
+    fn remove_sorted(&mut self, value: T) {
+        let len = self.sorted_len;
+        self.sorted_len = remove_sorted(&mut self.sorted, len, value);
+        if self.sorted_len < len {
+            self.sum = match self.sum {
+                Some(sum) if self.sorted_len > 0 => Some(value.remove_from_sum(sum)),
+                _ => None,
+            };
+        }
+    }
+
+    // Add up the sorted buffer from scratch, so rounding errors of the running sum cannot build up
+    fn resum(&mut self) {
+        let mut values = self.sorted[..self.sorted_len].iter();
+        self.sum = values
+            .next()
+            .map(|first| values.fold(first.to_sum(), |sum, value| value.add_to_sum(sum)));
+    }
+}
+
+impl<T: Sample, const N: usize> Default for MovingMedian<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Collects values into a new filter, as if [`add_value`](MovingMedian::add_value) was called for each.
+/// If there are more than N values, only the last N remain in the buffer.
+impl<T: Sample, const N: usize> FromIterator<T> for MovingMedian<T, N> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut filter = Self::new();
+        filter.extend(iter);
+        filter
+    }
+}
+
+/// Creates a full filter from an array, where the first element is the oldest value.
+impl<T: Sample, const N: usize> From<[T; N]> for MovingMedian<T, N> {
+    fn from(values: [T; N]) -> Self {
+        values.into_iter().collect()
+    }
+}
+
+/// Adds values to the filter, as if [`add_value`](MovingMedian::add_value) was called for each.
+impl<T: WindowSum, const N: usize> Extend<T> for MovingMedian<T, N> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for value in iter {
+            self.add_value(value);
+        }
+    }
+}
+
+/// Indexes the values in the buffer from the oldest at 0 to the newest at `len() - 1`,
+/// wherever in the buffer they are stored.
+///
+/// # Panics
+///
+/// Panics if the index is not less than [`len`](MovingMedian::len), like indexing a slice does.
+///
+/// ```
+/// use moving_median::MovingMedian;
+///
+/// let mut filter = MovingMedian::<i32, 3>::new();
+/// filter.add_values(&[1, 2, 3, 4]);
+/// assert_eq!((filter[0], filter[2]), (2, 4));
+/// ```
+impl<T: WindowSum, const N: usize> Index<usize> for MovingMedian<T, N> {
+    type Output = T;
+
+    fn index(&self, index: usize) -> &T {
+        assert!(
+            index < self.count,
+            "index out of bounds: the len is {} but the index is {}",
+            self.count,
+            index
+        );
+        &self.buffer[self.slot(index)]
+    }
+}
+
+/// Iterates over the values in the buffer from the oldest to the newest, like [`iter`](MovingMedian::iter).
+impl<'a, T: WindowSum, const N: usize> IntoIterator for &'a MovingMedian<T, N> {
+    type Item = &'a T;
+    type IntoIter = WindowIter<'a, T, N>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// An iterator over the values of a [`MovingMedian`] from the oldest to the newest,
+/// returned by [`MovingMedian::iter`].
+#[derive(Clone)]
+pub struct WindowIter<'a, T: WindowSum, const N: usize> {
+    filter: &'a MovingMedian<T, N>,
+    // Logical positions of the next values from the front and after the back
+    front: usize,
+    back: usize,
+}
+
+impl<'a, T: WindowSum, const N: usize> Iterator for WindowIter<'a, T, N> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        if self.front == self.back {
+            return None;
+        }
+        self.front += 1;
+        Some(&self.filter[self.front - 1])
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.back - self.front;
+        (len, Some(len))
+    }
+}
+
+impl<T: WindowSum, const N: usize> DoubleEndedIterator for WindowIter<'_, T, N> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.front == self.back {
+            return None;
+        }
+        self.back -= 1;
+        Some(&self.filter[self.back])
+    }
+}
+
+impl<T: WindowSum, const N: usize> ExactSizeIterator for WindowIter<'_, T, N> {}
+
+impl<T: WindowSum, const N: usize> FusedIterator for WindowIter<'_, T, N> {}
+
+/// Filters are equal if they hold the same values in the same order from oldest to newest,
+/// regardless of where in the buffer the values are stored.
+impl<T: WindowSum, const N: usize> PartialEq for MovingMedian<T, N> {
+    fn eq(&self, other: &Self) -> bool {
+        self.count == other.count && self.iter().eq(other.iter())
+    }
+}
+
+impl<T: WindowSum + Eq, const N: usize> Eq for MovingMedian<T, N> {}
+
+impl<T: WindowSum + fmt::Debug, const N: usize> fmt::Debug for MovingMedian<T, N> {
+    /// Shows the values in the buffer from oldest to newest, rather than in buffer order.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MovingMedian")
+            .field("window", &Window(self))
+            .field("count", &self.count)
+            .field("capacity", &N)
+            .field("index", &self.index)
+            .finish()
+    }
+}
+
+// Formats the values of a filter as a list from oldest to newest
+struct Window<'a, T: WindowSum, const N: usize>(&'a MovingMedian<T, N>);
+
+impl<T: WindowSum + fmt::Debug, const N: usize> fmt::Debug for Window<'_, T, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.0.iter()).finish()
+    }
+}
+
+/// Calculate the median of a slice of values without building a filter, or `None` if the slice
+/// holds no values other than NaN. The median is found like [`MovingMedian::median`] finds it,
+/// and NaN values are left out.
+///
+/// The slice is left untouched and no buffer is needed, at the cost of comparing every value with
+/// every other. For long slices that may be reordered, [`median_of_mut`] sorts in place instead.
+///
+/// ```
+/// use moving_median::median_of;
+///
+/// assert_eq!(median_of(&[3.0, 1.0, 2.0, 4.0]), Some(2.5));
+/// assert_eq!(median_of::<u8>(&[]), None);
+/// ```
+pub fn median_of<T: Sample>(values: &[T]) -> Option<T> {
+    let len = values.iter().filter(|value| !value.is_nan()).count();
+    if len == 0 {
+        return None;
+    }
+    let upper = nth_smallest(values, len / 2);
+    if len.is_multiple_of(2) {
+        Some(nth_smallest(values, len / 2 - 1).midpoint(upper))
+    } else {
+        Some(upper)
+    }
+}
+
+/// Calculate the median of a slice of values like [`median_of`], sorting the slice in place.
+/// Afterwards the slice holds its values in ascending order, followed by any NaN values.
+///
+/// ```
+/// use moving_median::median_of_mut;
+///
+/// let mut values = [5, 1, 4, 2];
+/// assert_eq!(median_of_mut(&mut values), Some(3));
+/// assert_eq!(values, [1, 2, 4, 5]);
+/// ```
+pub fn median_of_mut<T: Sample>(values: &mut [T]) -> Option<T> {
+    // Move the NaN values to the end, so the rest can be sorted
+    let mut len = 0;
+    for i in 0..values.len() {
+        if !values[i].is_nan() {
+            values.swap(len, i);
+            len += 1;
+        }
+    }
+    let sorted = &mut values[..len];
+    sorted.sort_unstable_by(|a, b| a.partial_cmp(b).unwrap());
+    middle(sorted, T::midpoint)
+}
+
+/// Run an N-wide [`MovingMedian`] across `input`, writing the median after each value into `out`.
+///
+/// The first N - 1 outputs are the medians of the partially filled window, exactly as stepping a
+/// filter by hand would give them, so `out[i]` is the median of `input[i + 1 - N..=i]` once `i + 1 >= N`
+/// and of `input[..=i]` before that.
+///
+/// # Panics
+///
+/// Panics if `out` is shorter than `input`.
+///
+/// ```
+/// use moving_median::windowed_medians;
+///
+/// let mut out = [0; 5];
+/// windowed_medians::<i32, 3>(&[5, 1, 9, 2, 7], &mut out);
+/// assert_eq!(out, [5, 3, 5, 2, 7]);
+/// ```
+pub fn windowed_medians<T: Sample, const N: usize>(input: &[T], out: &mut [T]) {
+    assert!(
+        out.len() >= input.len(),
+        "the output is shorter than the input"
+    );
+    let mut filter = MovingMedian::<T, N>::new();
+    for (&value, median) in input.iter().zip(out) {
+        filter.add_value(value);
+        *median = filter.median();
+    }
+}
+
+// The value at position `n` if the values other than NaN were sorted in ascending order
+fn nth_smallest<T: Sample>(values: &[T], n: usize) -> T {
+    *values
+        .iter()
+        .filter(|value| !value.is_nan())
+        .find(|&&value| {
+            let less = values.iter().filter(|&&other| other < value).count();
+            let equal = values.iter().filter(|&&other| other == value).count();
+            (less..less + equal).contains(&n)
+        })
+        .unwrap()
+}
+
+/// Find the median of values sorted in ascending order, or `None` if there are no values.
+/// The two middle values of an even number of values are combined with `average`.
+fn middle<T: Copy>(sorted: &[T], average: fn(T, T) -> T) -> Option<T> {
+    let len = sorted.len();
+    if len == 0 {
+        return None;
+    }
+
+    if len.is_multiple_of(2) {
+        // Even number of elements, take the average of the two middle elements
+        Some(average(sorted[len / 2 - 1], sorted[len / 2]))
+    } else {
+        // Odd number of elements, take the middle element
+        Some(sorted[len / 2])
+    }
+}
+
+/// Sort the values in ascending order of `less` using insertion sort.
+/// The sort is in-place, stable and close to linear on the nearly-sorted data a sliding window produces.
+fn insertion_sort_by<T: Copy>(values: &mut [T], less: impl Fn(&T, &T) -> bool) {
+    for i in 1..values.len() {
+        let value = values[i];
+        let mut j = i;
+        // Shift larger values one slot to the right until the gap is where `value` belongs
+        while j > 0 && less(&value, &values[j - 1]) {
+            values[j] = values[j - 1];
+            j -= 1;
+        }
+        values[j] = value;
+    }
+}
+
+/// The weighted median of `(value, weight)` pairs sorted by value, whose weights add up to `total`:
+/// the value at which the cumulative weight first reaches half of the total, or the average of the
+/// values on either side if it is exactly half. Returns `None` if there are no pairs.
+fn weighted_median_of<T: Sample, A: Copy + PartialOrd + Add<Output = A>>(
+    sorted_pairs: &[(T, A)],
+    total: A,
+) -> Option<T> {
+    // Walk the cumulative weight, comparing twice the cumulative weight to avoid halving the total
+    let mut cumulative = None;
+    for (i, &(value, weight)) in sorted_pairs.iter().enumerate() {
+        let sum = cumulative.map_or(weight, |cumulative| cumulative + weight);
+        cumulative = Some(sum);
+        let twice = sum + sum;
+        if twice > total {
+            return Some(value);
+        }
+        if twice == total {
+            return Some(match sorted_pairs.get(i + 1) {
+                Some(&(next, _)) => value.midpoint(next),
+                None => value,
+            });
+        }
+    }
+    None
+}
+
+/// Returns `true` if the value cannot be ordered, such as a floating point NaN.
+fn unordered<T: PartialOrd>(value: &T) -> bool {
+    value.partial_cmp(value).is_none()
+}
+
+/// Insert `value` into the ascending values `sorted[..len]`, shifting the larger ones up into
+/// `sorted[len]`, and return the new number of values. Values that cannot be ordered are left out.
+pub(crate) fn insert_sorted<T: Copy + PartialOrd>(sorted: &mut [T], len: usize, value: T) -> usize {
+    if unordered(&value) {
+        return len;
+    }
+    let position = lower_bound(&sorted[..len], value);
+    sorted.copy_within(position..len, position + 1);
+    sorted[position] = value;
+    len + 1
+}
+
+/// Remove `value`, which must be one of the ascending values `sorted[..len]`, and return the new
+/// number of values. Values that cannot be ordered were never inserted and are ignored.
+pub(crate) fn remove_sorted<T: Copy + PartialOrd>(sorted: &mut [T], len: usize, value: T) -> usize {
+    if unordered(&value) {
+        return len;
+    }
+    let position = lower_bound(&sorted[..len], value);
+    sorted.copy_within(position + 1..len, position);
+    len - 1
+}
+
+/// Find the first position in the sorted values that is not less than `value` using binary search.
+fn lower_bound<T: Copy + PartialOrd>(sorted: &[T], value: T) -> usize {
+    let mut low = 0;
+    let mut high = sorted.len();
+    while low < high {
+        let mid = low + (high - low) / 2;
+        if sorted[mid] < value {
+            low = mid + 1;
+        } else {
+            high = mid;
+        }
+    }
+    low
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use crate::test_util::XorShift;
+    use std::vec::Vec;
+
+    // Brute-force reference median using the standard library sort
+    fn reference_median(values: &[f64]) -> f64 {
+        let mut sorted: Vec<f64> = values.to_vec();
+        sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let len = sorted.len();
+        if len.is_multiple_of(2) {
+            (sorted[len / 2 - 1] + sorted[len / 2]) / 2.0
+        } else {
+            sorted[len / 2]
+        }
+    }
+
+    #[test]
+    fn median_is_zero_when_no_values_added() {
+        let filter = MovingMedian::<f64, 3>::new();
+        assert_eq!(filter.median(), 0.0);
+    }
+
+    #[test]
+    fn median_is_value_when_one_value_added() {
+        let mut filter = MovingMedian::<f64, 3>::new();
+        filter.add_value(42.0);
+        assert_eq!(filter.median(), 42.0);
+    }
+
+    #[test]
+    fn median_is_average_of_two_values_when_two_values_added() {
+        let mut filter = MovingMedian::<f64, 2>::new();
+        filter.add_value(42.0);
+        filter.add_value(43.0);
+        assert_eq!(filter.median(), 42.5);
+    }
+
+    #[test]
+    fn median_is_middle_value_when_three_values_added() {
+        let mut filter = MovingMedian::<f64, 3>::new();
+        filter.add_value(42.0);
+        filter.add_value(43.0);
+        filter.add_value(41.0);
+        assert_eq!(filter.median(), 42.0);
+    }
+
+    #[test]
+    fn median_is_average_of_two_middle_values_when_four_values_added() {
+        let mut filter = MovingMedian::<f64, 4>::new();
+        filter.add_value(42.0);
+        filter.add_value(43.0);
+        filter.add_value(41.0);
+        filter.add_value(44.0);
+        assert_eq!(filter.median(), 42.5);
+    }
+
+    #[test]
+    fn median_is_midlle_value_of_n_values_when_more_than_n_values_added() {
+        let mut filter = MovingMedian::<f64, 3>::new();
+        filter.add_value(42.0); // should be pushed out
+        filter.add_value(44.0);
+        filter.add_value(43.0); // should be the median
+        filter.add_value(41.0);
+        assert_eq!(filter.median(), 43.0);
+    }
+
+    #[test]
+    fn median_matches_reference_for_ascending_values() {
+        let mut filter = MovingMedian::<f64, 32>::new();
+        let values: Vec<f64> = (0..32).map(|i| i as f64).collect();
+        for &value in &values {
+            filter.add_value(value);
+        }
+        assert_eq!(filter.median(), reference_median(&values));
+    }
+
+    #[test]
+    fn try_median_is_none_when_no_values_added() {
+        let filter = MovingMedian::<f64, 3>::new();
+        assert_eq!(filter.try_median(), None);
+    }
+
+    #[test]
+    fn try_median_is_value_when_one_value_added() {
+        let mut filter = MovingMedian::<f64, 3>::new();
+        filter.add_value(42.0);
+        assert_eq!(filter.try_median(), Some(42.0));
+    }
+
+    #[test]
+    fn try_median_is_middle_value_when_three_values_added() {
+        let mut filter = MovingMedian::<f64, 3>::new();
+        filter.add_value(42.0);
+        filter.add_value(43.0);
+        filter.add_value(41.0);
+        assert_eq!(filter.try_median(), Some(42.0));
+        assert_eq!(filter.try_median(), Some(filter.median()));
+    }
+
+    #[test]
+    fn try_median_is_some_for_partially_filled_buffer() {
+        let mut filter = MovingMedian::<f64, 4>::new();
+        filter.add_value(0.0);
+        assert_eq!(filter.try_median(), Some(0.0));
+        filter.add_value(2.0);
+        assert_eq!(filter.try_median(), Some(1.0));
+    }
+
+    #[test]
+    fn median_matches_reference_for_random_values() {
+        let mut rng = XorShift(0x2545_f491_4f6c_dd1d);
+        let mut filter = MovingMedian::<f64, 16>::new();
+        let mut window = std::collections::VecDeque::new();
+        for _ in 0..10_000 {
+            // Draw from a small range so duplicate values are common
+            let value = (rng.next() % 50) as f64;
+            filter.add_value(value);
+            window.push_back(value);
+            if window.len() > 16 {
+                window.pop_front();
+            }
+            let values: Vec<f64> = window.iter().copied().collect();
+            assert_eq!(filter.median(), reference_median(&values));
+        }
+    }
+
+    #[test]
+    fn median_of_u8_rounds_down_between_middle_values() {
+        let mut filter = MovingMedian::<u8, 2>::new();
+        filter.add_value(254);
+        filter.add_value(255);
+        assert_eq!(filter.median(), 254);
+    }
+
+    #[test]
+    fn median_of_u16_rounds_down_between_middle_values() {
+        let mut filter = MovingMedian::<u16, 4>::new();
+        filter.add_value(1000);
+        filter.add_value(11);
+        filter.add_value(3);
+        filter.add_value(4);
+        assert_eq!(filter.median(), 7);
+    }
+
+    #[test]
+    fn median_of_i32_rounds_towards_negative_infinity() {
+        let mut filter = MovingMedian::<i32, 2>::new();
+        filter.add_value(3);
+        filter.add_value(4);
+        assert_eq!(filter.median(), 3);
+
+        filter.add_value(-3);
+        filter.add_value(-4);
+        assert_eq!(filter.median(), -4);
+    }
+
+    #[test]
+    fn median_of_i64_averages_middle_values_of_wrapped_window() {
+        let mut filter = MovingMedian::<i64, 4>::new();
+        for value in [100, -7, 2, 9, -1, 50] {
+            filter.add_value(value);
+        }
+        // Window is [2, 9, -1, 50], middle values are 2 and 9
+        assert_eq!(filter.median(), 5);
+    }
+
+    #[test]
+    fn median_supports_i8() {
+        let mut filter = MovingMedian::<i8, 3>::new();
+        filter.add_value(-128);
+        filter.add_value(127);
+        filter.add_value(-1);
+        assert_eq!(filter.median(), -1);
+    }
+
+    #[test]
+    fn len_is_empty_and_is_full_track_added_values() {
+        let mut filter = MovingMedian::<f64, 3>::new();
+        assert_eq!(filter.len(), 0);
+        assert!(filter.is_empty());
+        assert!(!filter.is_full());
+
+        filter.add_value(1.0);
+        assert_eq!(filter.len(), 1);
+        assert!(!filter.is_empty());
+        assert!(!filter.is_full());
+
+        filter.add_value(2.0);
+        assert_eq!(filter.len(), 2);
+        assert!(!filter.is_full());
+
+        filter.add_value(3.0);
+        assert_eq!(filter.len(), 3);
+        assert!(filter.is_full());
+
+        // Wrapping around keeps the buffer full
+        filter.add_value(4.0);
+        assert_eq!(filter.len(), 3);
+        assert!(!filter.is_empty());
+        assert!(filter.is_full());
+    }
+
+    #[test]
+    fn nan_is_ignored_at_start_of_window() {
+        let mut filter = MovingMedian::<f32, 4>::new();
+        filter.add_value(f32::NAN);
+        filter.add_value(1.0);
+        filter.add_value(3.0);
+        filter.add_value(2.0);
+        assert_eq!(filter.median(), 2.0);
+    }
+
+    #[test]
+    fn nan_is_ignored_in_middle_of_window() {
+        let mut filter = MovingMedian::<f32, 4>::new();
+        filter.add_value(1.0);
+        filter.add_value(f32::NAN);
+        filter.add_value(3.0);
+        filter.add_value(5.0);
+        assert_eq!(filter.median(), 3.0);
+    }
+
+    #[test]
+    fn nan_is_ignored_at_end_of_window() {
+        let mut filter = MovingMedian::<f64, 3>::new();
+        filter.add_value(1.0);
+        filter.add_value(2.0);
+        filter.add_value(f64::NAN);
+        assert_eq!(filter.median(), 1.5);
+        assert_eq!(filter.len(), 3);
+    }
+
+    #[test]
+    fn nan_leaves_window_when_evicted() {
+        let mut filter = MovingMedian::<f64, 3>::new();
+        filter.set_nan_policy(NanPolicy::Propagate);
+        filter.add_value(f64::NAN);
+        filter.add_value(1.0);
+        filter.add_value(2.0);
+        assert!(filter.median().is_nan());
+        filter.add_value(3.0);
+        assert_eq!(filter.median(), 2.0);
+    }
+
+    #[test]
+    fn nan_is_propagated_under_propagate_policy() {
+        let mut filter = MovingMedian::<f32, 3>::new();
+        filter.set_nan_policy(NanPolicy::Propagate);
+        filter.add_value(1.0);
+        filter.add_value(f32::NAN);
+        filter.add_value(3.0);
+        assert!(filter.try_median().unwrap().is_nan());
+    }
+
+    #[test]
+    fn window_of_only_nan_has_no_median_under_ignore_policy() {
+        let mut filter = MovingMedian::<f32, 3>::new();
+        for _ in 0..3 {
+            filter.add_value(f32::NAN);
+        }
+        assert_eq!(filter.try_median(), None);
+        assert_eq!(filter.median(), 0.0);
+    }
+
+    #[test]
+    fn window_of_only_nan_has_nan_median_under_propagate_policy() {
+        let mut filter = MovingMedian::<f32, 3>::new();
+        filter.set_nan_policy(NanPolicy::Propagate);
+        for _ in 0..3 {
+            filter.add_value(f32::NAN);
+        }
+        assert!(filter.try_median().unwrap().is_nan());
+    }
+
+    #[test]
+    fn sorted_buffer_matches_sorting_buffer_on_query() {
+        let mut rng = XorShift(0x9e37_79b9_7f4a_7c15);
+        let mut filter = MovingMedian::<f32, 129>::new();
+        for _ in 0..5_000 {
+            filter.add_value((rng.next() % 100_000) as f32 / 100.0);
+            // Sort a copy of the ring buffer on every query, as the filter used to
+            let mut sorted_on_query: Vec<f32> = filter.buffer[..filter.count].to_vec();
+            sorted_on_query.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            assert_eq!(&filter.sorted[..filter.sorted_len], &sorted_on_query[..]);
+            assert_eq!(
+                filter.try_median(),
+                middle(&sorted_on_query, <f32 as Sample>::midpoint)
+            );
+        }
+    }
+
+    const CONST_FILTER: MovingMedian<i32, 4> = MovingMedian::with_default(0);
+
+    #[test]
+    fn with_default_creates_empty_filter_in_const_context() {
+        let mut filter = CONST_FILTER;
+        assert!(filter.is_empty());
+        assert_eq!(filter.try_median(), None);
+        filter.add_value(7);
+        assert_eq!(filter.median(), 7);
+    }
+
+    #[test]
+    fn median_floor_and_ceil_round_between_positive_middle_values() {
+        let mut filter = MovingMedian::<i32, 2>::new();
+        filter.add_value(3);
+        filter.add_value(4);
+        assert_eq!(filter.median_floor(), Some(3));
+        assert_eq!(filter.median_ceil(), Some(4));
+    }
+
+    #[test]
+    fn median_floor_and_ceil_round_between_negative_middle_values() {
+        let mut filter = MovingMedian::<i32, 2>::new();
+        filter.add_value(-3);
+        filter.add_value(-4);
+        assert_eq!(filter.median(), -4);
+        assert_eq!(filter.median_floor(), Some(-4));
+        assert_eq!(filter.median_ceil(), Some(-3));
+    }
+
+    #[test]
+    fn median_floor_and_ceil_agree_for_odd_count_and_floats() {
+        let mut filter = MovingMedian::<i32, 3>::new();
+        assert_eq!(filter.median_floor(), None);
+        assert_eq!(filter.median_ceil(), None);
+        filter.add_value(-3);
+        filter.add_value(-4);
+        filter.add_value(10);
+        assert_eq!(filter.median_floor(), Some(-3));
+        assert_eq!(filter.median_ceil(), Some(-3));
+
+        let mut filter = MovingMedian::<f64, 2>::new();
+        filter.add_value(3.0);
+        filter.add_value(4.0);
+        assert_eq!(filter.median_floor(), Some(3.5));
+        assert_eq!(filter.median_ceil(), Some(3.5));
+    }
+
+    #[test]
+    fn add_value_returns_evicted_values_in_insertion_order() {
+        let mut filter = MovingMedian::<i32, 3>::new();
+        assert_eq!(filter.add_value(1), None);
+        assert_eq!(filter.add_value(2), None);
+        assert_eq!(filter.add_value(3), None);
+        assert_eq!(filter.add_value(4), Some(1));
+        assert_eq!(filter.add_value(5), Some(2));
+        assert_eq!(filter.add_value(6), Some(3));
+        assert_eq!(filter.add_value(7), Some(4));
+    }
+
+    #[test]
+    fn mean_is_zero_when_no_values_added() {
+        let filter = MovingMedian::<f64, 3>::new();
+        assert_eq!(filter.mean(), 0.0);
+    }
+
+    #[test]
+    fn mean_is_value_when_one_value_added() {
+        let mut filter = MovingMedian::<f64, 3>::new();
+        filter.add_value(42.0);
+        assert_eq!(filter.mean(), 42.0);
+    }
+
+    #[test]
+    fn mean_is_average_of_last_n_values() {
+        let mut filter = MovingMedian::<f64, 3>::new();
+        filter.add_value(100.0); // should be pushed out
+        filter.add_value(42.0);
+        filter.add_value(43.0);
+        filter.add_value(44.0);
+        assert_eq!(filter.mean(), 43.0);
+    }
+
+    #[test]
+    fn mean_differs_from_median_for_skewed_values() {
+        let mut filter = MovingMedian::<i32, 3>::new();
+        filter.add_value(1);
+        filter.add_value(2);
+        filter.add_value(100);
+        assert_eq!(filter.median(), 2);
+        assert_eq!(filter.mean(), 34);
+    }
+
+    #[test]
+    fn mean_does_not_overflow_for_large_windows_of_large_values() {
+        let mut filter = MovingMedian::<u8, 300>::new();
+        for _ in 0..300 {
+            filter.add_value(u8::MAX);
+        }
+        assert_eq!(filter.mean(), u8::MAX);
+    }
+
+    #[test]
+    fn is_full_becomes_true_on_nth_value_and_resets_after_clear() {
+        let mut filter = MovingMedian::<f64, 4>::new();
+        assert_eq!(filter.capacity(), 4);
+        for i in 1..=4 {
+            assert!(!filter.is_full());
+            filter.add_value(i as f64);
+            assert_eq!(filter.len(), i);
+        }
+        assert!(filter.is_full());
+
+        filter.clear();
+        assert_eq!(filter.len(), 0);
+        assert!(filter.is_empty());
+        assert!(!filter.is_full());
+        assert_eq!(filter.capacity(), 4);
+        assert_eq!(filter.try_median(), None);
+    }
+
+    #[test]
+    fn clear_starts_the_window_over() {
+        let mut filter = MovingMedian::<f64, 3>::new();
+        for value in [10.0, 20.0, 30.0, 40.0] {
+            filter.add_value(value);
+        }
+        filter.clear();
+        assert_eq!(filter.add_value(1.0), None);
+        filter.add_value(2.0);
+        assert_eq!(filter.median(), 1.5);
+    }
+
+    #[test]
+    fn min_and_max_are_none_when_no_values_added() {
+        let filter = MovingMedian::<f64, 3>::new();
+        assert_eq!(filter.min(), None);
+        assert_eq!(filter.max(), None);
+    }
+
+    #[test]
+    fn min_and_max_are_value_when_one_value_added() {
+        let mut filter = MovingMedian::<f64, 3>::new();
+        filter.add_value(42.0);
+        assert_eq!(filter.min(), Some(42.0));
+        assert_eq!(filter.max(), Some(42.0));
+    }
+
+    #[test]
+    fn min_and_max_only_consider_last_n_values() {
+        let mut filter = MovingMedian::<i32, 4>::new();
+        for value in [-50, 7, 3, 9, 5, 100] {
+            filter.add_value(value);
+        }
+        // Window is [3, 9, 5, 100]
+        assert_eq!(filter.min(), Some(3));
+        assert_eq!(filter.max(), Some(100));
+    }
+
+    #[test]
+    fn min_and_max_enclose_median_for_random_values() {
+        let mut rng = XorShift(0xdead_beef_cafe_f00d);
+        let mut filter = MovingMedian::<f64, 7>::new();
+        for _ in 0..1_000 {
+            filter.add_value((rng.next() % 1_000) as f64 - 500.0);
+            let median = filter.median();
+            assert!(filter.min().unwrap() <= median);
+            assert!(median <= filter.max().unwrap());
+        }
+    }
+
+    #[test]
+    fn min_and_max_ignore_unused_slots_of_partially_filled_buffer() {
+        let mut filter = MovingMedian::<i32, 5>::new();
+        filter.add_value(5);
+        filter.add_value(7);
+        // The three unused slots hold zero, which must not win as the minimum
+        assert_eq!(filter.min(), Some(5));
+
+        let mut filter = MovingMedian::<f32, 5>::new();
+        filter.add_value(-5.0);
+        filter.add_value(-7.0);
+        assert_eq!(filter.max(), Some(-5.0));
+    }
+
+    #[test]
+    fn default_creates_empty_filter() {
+        let filter = MovingMedian::<f64, 3>::default();
+        assert!(filter.is_empty());
+        assert_eq!(filter.median(), 0.0);
+    }
+
+    #[test]
+    fn quantile_is_none_when_no_values_added_or_out_of_range() {
+        let mut filter = MovingMedian::<f64, 3>::new();
+        assert_eq!(filter.quantile(0.5, Interpolation::Linear), None);
+        filter.add_value(1.0);
+        assert_eq!(filter.quantile(-0.1, Interpolation::Linear), None);
+        assert_eq!(filter.quantile(1.1, Interpolation::Nearest), None);
+        assert_eq!(filter.quantile(f64::NAN, Interpolation::Nearest), None);
+    }
+
+    #[test]
+    fn quantile_of_zero_and_one_are_min_and_max() {
+        let mut filter = MovingMedian::<i32, 5>::new();
+        for value in [7, -2, 30, 4, 11] {
+            filter.add_value(value);
+        }
+        for interpolation in [Interpolation::Nearest, Interpolation::Linear] {
+            assert_eq!(filter.quantile(0.0, interpolation), filter.min());
+            assert_eq!(filter.quantile(1.0, interpolation), filter.max());
+        }
+    }
+
+    #[test]
+    fn quantile_of_half_is_median() {
+        let mut rng = XorShift(0x0123_4567_89ab_cdef);
+        let mut filter = MovingMedian::<f64, 6>::new();
+        let mut integer_filter = MovingMedian::<i16, 6>::new();
+        for _ in 0..100 {
+            let value = (rng.next() % 1_000) as i16 - 500;
+            filter.add_value(value as f64 / 8.0);
+            integer_filter.add_value(value);
+            assert_eq!(
+                filter.quantile(0.5, Interpolation::Linear),
+                filter.try_median()
+            );
+            assert_eq!(
+                integer_filter.quantile(0.5, Interpolation::Linear),
+                integer_filter.try_median()
+            );
+        }
+    }
+
+    #[test]
+    fn quantile_interpolates_between_neighbours() {
+        let mut filter = MovingMedian::<u16, 8>::new();
+        for value in [10, 20, 30, 40, 50] {
+            filter.add_value(value);
+        }
+        // Rank 0.9 lies between 10 and 20
+        assert_eq!(filter.quantile(0.225, Interpolation::Linear), Some(19));
+        assert_eq!(filter.quantile(0.225, Interpolation::Nearest), Some(20));
+        assert_eq!(filter.quantile(0.1, Interpolation::Nearest), Some(10));
+        assert_eq!(filter.quantile(0.75, Interpolation::Linear), Some(40));
+    }
+
+    #[test]
+    fn quantile_uses_only_values_of_partially_filled_buffer() {
+        let mut filter = MovingMedian::<f64, 10>::new();
+        filter.add_value(5.0);
+        filter.add_value(15.0);
+        assert_eq!(filter.quantile(0.0, Interpolation::Nearest), Some(5.0));
+        assert_eq!(filter.quantile(0.25, Interpolation::Linear), Some(7.5));
+        assert_eq!(filter.quantile(1.0, Interpolation::Linear), Some(15.0));
+    }
+
+    #[test]
+    fn add_values_keeps_only_last_n_values() {
+        let mut filter = MovingMedian::<i32, 3>::new();
+        filter.add_values(&[5, 1, 9, 2, 7]);
+        assert_eq!(filter.len(), 3);
+        assert_eq!(filter.median(), 7);
+        // The window is [9, 2, 7], which is evicted oldest first
+        assert_eq!(filter.add_value(0), Some(9));
+        assert_eq!(filter.add_value(0), Some(2));
+        assert_eq!(filter.add_value(0), Some(7));
+    }
+
+    // Brute-force reference median absolute deviation
+    fn reference_mad(values: &[f64]) -> f64 {
+        let median = reference_median(values);
+        let deviations: Vec<f64> = values.iter().map(|value| (value - median).abs()).collect();
+        reference_median(&deviations)
+    }
+
+    #[test]
+    fn mad_is_none_when_no_values_added() {
+        let filter = MovingMedian::<f64, 3>::new();
+        assert_eq!(filter.mad(), None);
+    }
+
+    #[test]
+    fn mad_stays_small_with_outlier() {
+        let mut filter = MovingMedian::<i32, 7>::new();
+        filter.add_values(&[10, 11, 10, 12, 1000, 11, 10]);
+        assert_eq!(filter.median(), 11);
+        assert_eq!(filter.mad(), Some(1));
+        // The outlier pulls the mean far away from the typical values
+        assert_eq!(filter.mean(), 152);
+    }
+
+    #[test]
+    fn mad_only_considers_values_of_partially_filled_buffer() {
+        let mut filter = MovingMedian::<f32, 8>::new();
+        filter.add_values(&[1.0, 2.0, 4.0]);
+        // Deviations from the median 2 are [1, 0, 2]
+        assert_eq!(filter.mad(), Some(1.0));
+    }
+
+    #[test]
+    fn mad_saturates_signed_deviations() {
+        let mut filter = MovingMedian::<i8, 3>::new();
+        filter.add_values(&[-128, 127, 127]);
+        assert_eq!(filter.mad(), Some(0));
+        filter.add_values(&[-128, -128]);
+        assert_eq!(filter.mad(), Some(0));
+        let mut filter = MovingMedian::<i8, 2>::new();
+        filter.add_values(&[-128, 127]);
+        assert_eq!(filter.mad(), Some(127));
+    }
+
+    #[test]
+    fn mad_matches_reference_for_random_values() {
+        let mut rng = XorShift(0x5851_f42d_4c95_7f2d);
+        let mut filter = MovingMedian::<f64, 9>::new();
+        let mut window = std::collections::VecDeque::new();
+        for _ in 0..2_000 {
+            let value = (rng.next() % 64) as f64;
+            filter.add_value(value);
+            window.push_back(value);
+            if window.len() > 9 {
+                window.pop_front();
+            }
+            let values: Vec<f64> = window.iter().copied().collect();
+            assert_eq!(filter.mad(), Some(reference_mad(&values)));
+        }
+    }
+
+    #[test]
+    fn clone_is_independent_of_original() {
+        let mut filter = MovingMedian::<f64, 3>::new();
+        filter.add_values(&[1.0, 2.0, 3.0]);
+        let mut snapshot = filter.clone();
+        snapshot.add_values(&[100.0, 200.0]);
+        assert_eq!(snapshot.median(), 100.0);
+        assert_eq!(filter.median(), 2.0);
+    }
+
+    #[test]
+    fn debug_shows_window_from_oldest_to_newest() {
+        let mut filter = MovingMedian::<f64, 3>::new();
+        filter.add_values(&[1.0, 2.0]);
+        assert_eq!(
+            std::format!("{:?}", filter),
+            "MovingMedian { window: [1.0, 2.0], count: 2, capacity: 3, index: 2 }"
+        );
+        filter.add_values(&[3.0, 4.0]);
+        assert_eq!(
+            std::format!("{:?}", filter),
+            "MovingMedian { window: [2.0, 3.0, 4.0], count: 3, capacity: 3, index: 1 }"
+        );
+    }
+
+    #[test]
+    fn iter_yields_values_from_oldest_to_newest() {
+        let mut filter = MovingMedian::<i32, 3>::new();
+        assert_eq!(filter.iter().count(), 0);
+        filter.add_values(&[1, 2]);
+        assert_eq!(filter.iter().copied().collect::<Vec<_>>(), [1, 2]);
+        filter.add_values(&[3, 4, 5]);
+        assert_eq!(filter.iter().copied().collect::<Vec<_>>(), [3, 4, 5]);
+    }
+
+    #[test]
+    fn percentile_matches_median_min_and_max() {
+        let mut filter = MovingMedian::<f64, 4>::new();
+        assert_eq!(filter.percentile(50.0), None);
+        filter.add_values(&[12.0, 3.0, 40.0, 7.0]);
+        assert_eq!(filter.percentile(50.0), Some(filter.median()));
+        assert_eq!(filter.percentile(0.0), filter.min());
+        assert_eq!(filter.percentile(100.0), filter.max());
+    }
+
+    #[test]
+    fn percentile_clamps_out_of_range_values() {
+        let mut filter = MovingMedian::<i32, 5>::new();
+        filter.add_values(&[1, 2, 3, 4, 5]);
+        assert_eq!(filter.percentile(-10.0), Some(1));
+        assert_eq!(filter.percentile(250.0), Some(5));
+        assert_eq!(filter.percentile(90.0), Some(4));
+    }
+
+    #[test]
+    fn debug_shows_wrapped_window_in_chronological_order() {
+        let mut filter = MovingMedian::<i32, 3>::new();
+        filter.add_values(&[1, 2, 3, 4]);
+        // The buffer holds [4, 2, 3], but 4 is the newest value
+        assert_eq!(
+            std::format!("{:?}", filter),
+            "MovingMedian { window: [2, 3, 4], count: 3, capacity: 3, index: 1 }"
+        );
+    }
+
+    #[test]
+    fn collect_keeps_only_last_n_values() {
+        let mut filter: MovingMedian<i32, 4> = (1..=10).collect();
+        assert_eq!(filter.iter().copied().collect::<Vec<_>>(), [7, 8, 9, 10]);
+        assert_eq!(filter.median(), 8);
+        // The next value evicts the oldest collected value
+        assert_eq!(filter.add_value(11), Some(7));
+    }
+
+    #[test]
+    fn filters_with_same_window_at_different_offsets_are_equal() {
+        let filled: MovingMedian<i32, 3> = [1, 2, 3].into_iter().collect();
+        let wrapped: MovingMedian<i32, 3> = [8, 9, 1, 2, 3].into_iter().collect();
+        assert_eq!(filled, wrapped);
+        assert_ne!(filled, [1, 2, 4].into_iter().collect());
+        assert_ne!(filled, [3, 2, 1].into_iter().collect());
+    }
+
+    #[test]
+    fn filters_reaching_same_window_with_different_pushes_are_equal() {
+        let mut wrapped = MovingMedian::<f32, 4>::new();
+        for value in 1..=7 {
+            wrapped.add_value(value as f32);
+        }
+        let mut direct = MovingMedian::<f32, 4>::new();
+        for value in [4.0, 5.0, 6.0, 7.0] {
+            direct.add_value(value);
+        }
+        assert_eq!(wrapped, direct);
+        // Both evict the same value next, so they stay equal
+        assert_eq!(wrapped.add_value(8.0), direct.add_value(8.0));
+        assert_eq!(wrapped, direct);
+    }
+
+    #[test]
+    fn filters_with_different_counts_are_not_equal() {
+        // Both buffers hold [1, 2, 0], but only one filter has three values
+        let partial: MovingMedian<i32, 3> = [1, 2].into_iter().collect();
+        let full: MovingMedian<i32, 3> = [1, 2, 0].into_iter().collect();
+        assert_ne!(partial, full);
+        assert_eq!(partial, partial.clone());
+    }
+
+    #[test]
+    fn extend_adds_values_like_add_value() {
+        let mut filter = MovingMedian::<f64, 3>::new();
+        filter.add_value(100.0);
+        filter.extend([1.0, 5.0].iter().copied());
+        assert_eq!(filter.median(), 5.0);
+        filter.extend((0..2).map(|i| i as f64));
+        // Window is [5, 0, 1]
+        assert_eq!(filter.median(), 1.0);
+        assert_eq!(filter.add_value(2.0), Some(5.0));
+    }
+
+    #[test]
+    fn iter_knows_its_length_and_can_be_reversed() {
+        let mut filter = MovingMedian::<i32, 3>::new();
+        assert_eq!(filter.iter().len(), 0);
+        assert_eq!(filter.iter().next_back(), None);
+
+        filter.add_values(&[1, 2]);
+        assert_eq!(filter.iter().len(), 2);
+        assert_eq!(filter.iter().rev().copied().collect::<Vec<_>>(), [2, 1]);
+
+        filter.add_value(3);
+        assert_eq!(filter.iter().len(), 3);
+        assert_eq!(filter.iter().copied().collect::<Vec<_>>(), [1, 2, 3]);
+        assert_eq!(filter.iter().rev().copied().collect::<Vec<_>>(), [3, 2, 1]);
+
+        filter.add_values(&[4, 5]);
+        assert_eq!(filter.iter().len(), 3);
+        assert_eq!(filter.iter().copied().collect::<Vec<_>>(), [3, 4, 5]);
+        assert_eq!(filter.iter().rev().copied().collect::<Vec<_>>(), [5, 4, 3]);
+    }
+
+    #[test]
+    fn capacity_is_n() {
+        assert_eq!(MovingMedian::<f32, 1>::new().capacity(), 1);
+        assert_eq!(MovingMedian::<u16, 9>::new().capacity(), 9);
+        assert_eq!(MovingMedian::<f64, 129>::new().capacity(), 129);
+    }
+
+    #[test]
+    fn iter_sorted_yields_window_in_ascending_order() {
+        let mut filter = MovingMedian::<i32, 4>::new();
+        filter.add_values(&[9, -1, 4]);
+        // The unused slot must not show up as a zero
+        assert_eq!(filter.iter_sorted().collect::<Vec<_>>(), [-1, 4, 9]);
+        filter.add_values(&[7, 3, 3]);
+        // Window is [4, 7, 3, 3]
+        assert_eq!(filter.iter_sorted().collect::<Vec<_>>(), [3, 3, 4, 7]);
+        assert_eq!(filter.iter_sorted().len(), 4);
+    }
+
+    #[test]
+    fn iter_sorted_leaves_out_nan() {
+        let mut filter = MovingMedian::<f32, 3>::new();
+        filter.add_values(&[2.0, f32::NAN, 1.0]);
+        assert_eq!(filter.iter_sorted().collect::<Vec<_>>(), [1.0, 2.0]);
+    }
+
+    #[test]
+    fn from_array_creates_full_filter_evicting_first_element() {
+        let mut filter = MovingMedian::from([5, 1, 3]);
+        assert!(filter.is_full());
+        assert_eq!(filter.median(), 3);
+        assert_eq!(filter.add_value(10), Some(5));
+        assert_eq!(filter.add_value(10), Some(1));
+        assert_eq!(filter.iter().copied().collect::<Vec<_>>(), [3, 10, 10]);
+    }
+
+    #[test]
+    fn collect_from_short_iterator_continues_ring() {
+        let mut filter: MovingMedian<i32, 4> = [1, 2].into_iter().collect();
+        assert_eq!(filter.add_value(3), None);
+        assert_eq!(filter.add_value(4), None);
+        assert_eq!(filter.add_value(5), Some(1));
+    }
+
+    #[test]
+    fn median_ignore_nan_ignores_nan_under_any_policy() {
+        let mut filter = MovingMedian::<f64, 3>::new();
+        filter.set_nan_policy(NanPolicy::Propagate);
+        filter.add_values(&[1.0, f64::NAN, 3.0]);
+        assert!(filter.median().is_nan());
+        assert_eq!(filter.median_ignore_nan(), Some(2.0));
+
+        filter.set_nan_policy(NanPolicy::Ignore);
+        assert_eq!(filter.median(), 2.0);
+        assert_eq!(filter.median_ignore_nan(), Some(2.0));
+
+        filter.add_values(&[f64::NAN, f64::NAN, f64::NAN]);
+        assert_eq!(filter.median_ignore_nan(), None);
+    }
+
+    #[test]
+    fn add_values_fast_path_matches_adding_one_by_one() {
+        let mut rng = XorShift(0x1234_5678_9abc_def0);
+        for len in [0, 1, 4, 5, 6, 11, 32] {
+            let values: Vec<f32> = (0..len)
+                .map(|_| match rng.next() % 10 {
+                    0 => f32::NAN,
+                    value => value as f32,
+                })
+                .collect();
+            let mut fast = MovingMedian::<f32, 5>::new();
+            let mut plain = MovingMedian::<f32, 5>::new();
+            fast.add_values(&[1.0, 2.0]);
+            plain.add_values(&[1.0, 2.0]);
+            fast.add_values(&values);
+            for &value in &values {
+                plain.add_value(value);
+            }
+            assert_eq!(std::format!("{:?}", fast), std::format!("{:?}", plain));
+            assert_eq!(fast.median(), plain.median());
+            assert_eq!(
+                fast.iter().map(|value| value.to_bits()).collect::<Vec<_>>(),
+                plain
+                    .iter()
+                    .map(|value| value.to_bits())
+                    .collect::<Vec<_>>()
+            );
+            assert_eq!(
+                fast.iter_sorted().collect::<Vec<_>>(),
+                plain.iter_sorted().collect::<Vec<_>>()
+            );
+            // Both evict the same oldest values afterwards
+            for value in [7.0, 8.0, 9.0] {
+                assert_eq!(
+                    fast.add_value(value).map(f32::to_bits),
+                    plain.add_value(value).map(f32::to_bits)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn new_filled_starts_full_and_converges() {
+        let mut filter = MovingMedian::<f32, 5>::new_filled(20.0);
+        assert!(filter.is_full());
+        assert_eq!(filter.len(), 5);
+        assert_eq!(filter.median(), 20.0);
+        let medians: Vec<f32> = (0..5)
+            .map(|_| {
+                assert_eq!(filter.add_value(25.0), Some(20.0));
+                filter.median()
+            })
+            .collect();
+        // The median moves to the new readings once they are the majority of the window
+        assert_eq!(medians, [20.0, 20.0, 25.0, 25.0, 25.0]);
+    }
+
+    #[test]
+    fn new_filled_with_nan_has_no_median() {
+        let mut filter = MovingMedian::<f64, 3>::new_filled(f64::NAN);
+        assert!(filter.is_full());
+        assert_eq!(filter.try_median(), None);
+        filter.add_value(1.0);
+        assert_eq!(filter.try_median(), Some(1.0));
+    }
+
+    #[test]
+    fn reset_to_fills_window_with_value() {
+        let mut filter = MovingMedian::<f64, 4>::new();
+        filter.add_values(&[1.0, 9.0, 3.0]);
+        filter.reset_to(5.0);
+        assert_eq!(filter.median(), 5.0);
+        assert!(filter.is_full());
+        assert_eq!(filter.iter().copied().collect::<Vec<_>>(), [5.0; 4]);
+        filter.add_value(7.0);
+        filter.add_value(7.0);
+        filter.add_value(7.0);
+        assert_eq!(filter.median(), 7.0);
+    }
+
+    #[test]
+    fn peek_oldest_and_newest_follow_the_window() {
+        let mut filter = MovingMedian::<i32, 3>::new();
+        assert_eq!(filter.peek_oldest(), None);
+        assert_eq!(filter.peek_newest(), None);
+        let expected = [(1, 1), (1, 2), (1, 3), (2, 4), (3, 5)];
+        for (value, (oldest, newest)) in (1..=5).zip(expected) {
+            filter.add_value(value);
+            assert_eq!(filter.peek_oldest(), Some(oldest));
+            assert_eq!(filter.peek_newest(), Some(newest));
+        }
+        // The oldest value is the one evicted next
+        assert_eq!(filter.add_value(6), Some(3));
+    }
+
+    #[test]
+    fn range_is_max_minus_min() {
+        let mut filter = MovingMedian::<f64, 4>::new();
+        assert_eq!(filter.range(), None);
+        filter.add_value(3.5);
+        assert_eq!(filter.range(), Some(0.0));
+        filter.add_values(&[-1.0, 7.0, 2.0]);
+        assert_eq!(filter.range(), Some(8.0));
+        // The minimum is evicted
+        filter.add_value(4.0);
+        filter.add_value(4.0);
+        assert_eq!(filter.range(), Some(5.0));
+
+        let mut filter = MovingMedian::<i8, 2>::new();
+        filter.add_values(&[-100, 100]);
+        assert_eq!(filter.range(), Some(i8::MAX));
+    }
+
+    #[test]
+    fn variance_of_known_values() {
+        let mut filter = MovingMedian::<f64, 8>::new();
+        assert_eq!(filter.variance(), None);
+        filter.add_values(&[2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0]);
+        assert_eq!(filter.variance(), Some(4.0));
+
+        let mut filter = MovingMedian::<i32, 8>::new();
+        filter.add_values(&[2, 4, 4, 4, 5, 5, 7, 9]);
+        assert_eq!(filter.variance(), Some(4));
+        // 0.25 is rounded down
+        filter.clear();
+        filter.add_values(&[1, 2]);
+        assert_eq!(filter.variance(), Some(0));
+    }
+
+    #[test]
+    fn variance_of_partially_filled_buffer_ignores_padding() {
+        // The unused slots hold zeros, which would pull the mean down and the variance up
+        let mut filter = MovingMedian::<f32, 16>::new();
+        filter.add_values(&[98.0, 102.0, 100.0, 100.0]);
+        assert_eq!(filter.variance(), Some(2.0));
+        // And slots filled with a seed value must not pull it towards zero
+        let mut filter = MovingMedian::<f64, 6>::new_filled(50.0);
+        filter.clear();
+        filter.add_values(&[1.0, 3.0]);
+        assert_eq!(filter.variance(), Some(1.0));
+        // Once the window wraps, only the last N values count
+        let mut filter = MovingMedian::<i64, 3>::new();
+        filter.add_values(&[1_000, -1_000, 6, 8, 10]);
+        assert_eq!(filter.variance(), Some(2));
+    }
+
+    #[test]
+    fn variance_handles_nan_according_to_policy() {
+        let mut filter = MovingMedian::<f32, 3>::new();
+        filter.add_values(&[1.0, f32::NAN, 3.0]);
+        assert_eq!(filter.variance(), Some(1.0));
+        filter.set_nan_policy(NanPolicy::Propagate);
+        assert!(filter.variance().unwrap().is_nan());
+    }
+
+    #[cfg(feature = "libm")]
+    #[test]
+    fn std_dev_of_known_values() {
+        let mut filter = MovingMedian::<f64, 8>::new();
+        assert_eq!(filter.std_dev(), None);
+        filter.add_values(&[2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0]);
+        assert_eq!(filter.std_dev(), Some(2.0));
+
+        // The square root is taken before rounding, so a variance of 2.25 gives 1 rather than 1.41
+        let mut filter = MovingMedian::<u8, 2>::new();
+        filter.add_values(&[1, 4]);
+        assert_eq!(filter.variance(), Some(2));
+        assert_eq!(filter.std_dev(), Some(1));
+
+        // Only the values added so far are used
+        let mut filter = MovingMedian::<f32, 10>::new();
+        filter.add_values(&[10.0, 16.0]);
+        assert_eq!(filter.std_dev(), Some(3.0));
+    }
+
+    #[test]
+    fn median_of_slice_matches_filter() {
+        assert_eq!(median_of::<f64>(&[]), None);
+        assert_eq!(median_of(&[42.0]), Some(42.0));
+        assert_eq!(median_of(&[42.0, 43.0, 41.0]), Some(42.0));
+        assert_eq!(median_of(&[1.0, f64::NAN, 3.0]), Some(2.0));
+        assert_eq!(median_of(&[f32::NAN]), None);
+        assert_eq!(median_of(&[u16::MAX, u16::MAX - 1]), Some(u16::MAX - 1));
+
+        let mut rng = XorShift(0x2545_f491_4f6c_dd1d);
+        for len in 0..40 {
+            // Draw from a small range so duplicate values are common
+            let values: Vec<i32> = (0..len).map(|_| (rng.next() % 10) as i32 - 5).collect();
+            let filter: MovingMedian<i32, 40> = values.iter().copied().collect();
+            assert_eq!(median_of(&values), filter.try_median());
+            let mut sorted = values.clone();
+            assert_eq!(median_of_mut(&mut sorted), filter.try_median());
+            assert!(sorted.windows(2).all(|pair| pair[0] <= pair[1]));
+        }
+    }
+
+    #[test]
+    fn median_of_mut_moves_nan_to_the_end() {
+        let mut values = [f32::NAN, 3.0, f32::NAN, 1.0, 2.0];
+        assert_eq!(median_of_mut(&mut values), Some(2.0));
+        assert_eq!(values[..3], [1.0, 2.0, 3.0]);
+        assert!(values[3..].iter().all(|value| value.is_nan()));
+    }
+
+    #[test]
+    fn windowed_medians_match_stepping_a_filter() {
+        let mut rng = XorShift(0x9e37_79b9_7f4a_7c15);
+        let input: Vec<f64> = (0..200).map(|_| (rng.next() % 100) as f64).collect();
+        let mut out = [f64::NAN; 210];
+        windowed_medians::<f64, 6>(&input, &mut out);
+        let mut filter = MovingMedian::<f64, 6>::new();
+        for (i, &value) in input.iter().enumerate() {
+            filter.add_value(value);
+            assert_eq!(out[i], filter.median());
+        }
+        // Outputs past the end of the input are left alone
+        assert!(out[200..].iter().all(|value| value.is_nan()));
+    }
+
+    #[test]
+    #[should_panic]
+    fn windowed_medians_panics_for_short_output() {
+        windowed_medians::<u8, 2>(&[1, 2, 3], &mut [0; 2]);
+    }
+
+    #[test]
+    fn trimmed_mean_leaves_out_extremes() {
+        let mut filter = MovingMedian::<i32, 7>::new();
+        assert_eq!(filter.trimmed_mean(0), None);
+        // A partially filled window
+        filter.add_values(&[1, 100, 2, 3, -50]);
+        assert_eq!(filter.trimmed_mean(0), Some(filter.mean()));
+        assert_eq!(filter.trimmed_mean(1), Some(2));
+        assert_eq!(filter.trimmed_mean(2), filter.try_median());
+        assert_eq!(filter.trimmed_mean(3), None);
+
+        filter.add_values(&[i32::MAX, i32::MAX]);
+        assert_eq!(filter.trimmed_mean(3), Some(3));
+        assert_eq!(filter.trimmed_mean(2), Some(35));
+        // The sum of the remaining values does not fit into an i32
+        assert_eq!(filter.trimmed_mean(1), Some(429_496_750));
+    }
+
+    #[test]
+    fn median_by_reverse_order_matches_median() {
+        let mut rng = XorShift(0x2545_f491_4f6c_dd1d);
+        let mut filter = MovingMedian::<i64, 9>::new();
+        assert_eq!(filter.median_by(|a, b| b.cmp(a)), None);
+        for _ in 0..500 {
+            filter.add_value((rng.next() % 100) as i64);
+            if filter.len() % 2 == 1 {
+                assert_eq!(filter.median_by(|a, b| b.cmp(a)), filter.try_median());
+            }
+        }
+        // Order by distance from 50, so the middle value is the one with the median distance
+        filter.add_values(&[50, 38, 70, 45, 10, 95, 52, 60, 49]);
+        let by_distance = filter.median_by(|a, b| a.abs_diff(50).cmp(&b.abs_diff(50)));
+        assert_eq!(by_distance, Some(60));
+    }
+
+    #[test]
+    fn is_outlier_compares_deviation_with_mad() {
+        let mut filter = MovingMedian::<f64, 4>::new();
+        assert!(!filter.is_outlier(100.0, 3.0));
+        filter.add_values(&[10.0, 11.0, 10.0, 12.0]);
+        // The median is 10.5 and the MAD 0.5
+        assert!(filter.is_outlier(100.0, 3.0));
+        assert!(!filter.is_outlier(11.0, 3.0));
+        assert!(filter.is_outlier(12.5, 3.0));
+        assert!(!filter.is_outlier(12.0, 3.0));
+    }
+
+    #[test]
+    fn median_low_and_high_pick_middle_values() {
+        let mut filter = MovingMedian::<i32, 4>::new();
+        assert_eq!(filter.median_low(), None);
+        assert_eq!(filter.median_high(), None);
+        filter.add_values(&[10, 3, 4]);
+        assert_eq!(filter.median_low(), Some(4));
+        assert_eq!(filter.median_high(), Some(4));
+        filter.add_value(11);
+        assert_eq!(filter.median_low(), Some(4));
+        assert_eq!(filter.median_high(), Some(10));
+    }
+
+    #[test]
+    fn ordered_type_without_arithmetic_can_be_filtered() {
+        // Only ordered, so there is no `Sample` implementation and the window keeps no sum
+        #[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Default)]
+        struct Tick(u32);
+
+        impl WindowSum for Tick {
+            type Sum = ();
+
+            fn to_sum(self) {}
+        }
+
+        let mut filter = MovingMedian::<Tick, 4>::with_default(Tick::default());
+        for tick in [5, 1, 9, 7] {
+            filter.add_value(Tick(tick));
+        }
+        assert_eq!(filter.median_low(), Some(Tick(5)));
+        assert_eq!(filter.median_high(), Some(Tick(7)));
+        assert_eq!(filter.min(), Some(Tick(1)));
+        filter.add_value(Tick(2));
+        assert_eq!(filter.median_low(), Some(Tick(2)));
+        assert_eq!(filter.median_high(), Some(Tick(7)));
+    }
+
+    #[test]
+    fn even_strategy_picks_median_of_even_window() {
+        let mut filter = MovingMedian::<i32, 5>::new();
+        filter.add_values(&[3, 4, 10, 11]);
+        assert_eq!(filter.even_strategy(), EvenStrategy::AverageMiddle);
+        assert_eq!(filter.median(), 7);
+        filter.set_even_strategy(EvenStrategy::TakeLower);
+        assert_eq!(filter.median(), 4);
+        assert_eq!(filter.median_ignore_nan(), Some(4));
+        filter.set_even_strategy(EvenStrategy::TakeUpper);
+        assert_eq!(filter.median(), 10);
+        // Odd counts are unaffected
+        filter.add_value(1);
+        assert_eq!(filter.median(), 4);
+        filter.clear();
+        assert_eq!(filter.try_median(), None);
+    }
+
+    #[test]
+    fn mode_is_most_frequent_value() {
+        let mut filter = MovingMedian::<u8, 6>::new();
+        assert_eq!(filter.mode(), None);
+        filter.add_values(&[3, 1, 3, 2, 3, 2]);
+        assert_eq!(filter.mode(), Some(3));
+        // All values are distinct, so the smallest wins the tie
+        filter.add_values(&[9, 4, 7, 5, 8, 6]);
+        assert_eq!(filter.mode(), Some(4));
+        // Two values are equally frequent
+        filter.add_values(&[7, 5, 7, 5]);
+        assert_eq!(filter.mode(), Some(5));
+    }
+
+    #[test]
+    fn mode_of_partially_filled_buffer() {
+        #[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+        enum Gear {
+            Reverse,
+            Neutral,
+            First,
+        }
+
+        impl WindowSum for Gear {
+            type Sum = ();
+
+            fn to_sum(self) {}
+        }
+
+        // The unused slots hold the default, which must not count
+        let mut filter = MovingMedian::<Gear, 8>::with_default(Gear::Reverse);
+        filter.add_values(&[Gear::First, Gear::Neutral, Gear::First]);
+        assert_eq!(filter.mode(), Some(Gear::First));
+
+        let mut filter = MovingMedian::<f32, 5>::new();
+        filter.add_values(&[f32::NAN, 2.5, f32::NAN]);
+        assert_eq!(filter.mode(), Some(2.5));
+    }
+
+    #[test]
+    fn median_by_key_returns_whole_value() {
+        let mut filter = MovingMedian::<(u32, f32), 4>::with_default((0, 0.0));
+        assert_eq!(filter.median_by_key(|&(_, reading)| reading), None);
+        for (timestamp, reading) in [(1, 5.0), (2, 1.0), (3, 9.0), (4, 3.0), (5, f32::NAN)] {
+            filter.add_value((timestamp, reading));
+        }
+        // The NaN reading is left out of the readings 1, 9 and 3
+        assert_eq!(
+            filter.median_by_key(|&(_, reading)| reading),
+            Some((4, 3.0))
+        );
+        filter.add_value((6, 2.0));
+        // Readings 9, 3 and 2 after 1 is evicted
+        assert_eq!(
+            filter.median_by_key(|&(_, reading)| reading),
+            Some((4, 3.0))
+        );
+        filter.add_value((7, 8.0));
+        // Readings 3, 2 and 8
+        assert_eq!(
+            filter.median_by_key(|&(_, reading)| reading),
+            Some((4, 3.0))
+        );
+        // Timestamps 4 to 7 are an even number, so the lower middle one is taken
+        let by_time = filter.median_by_key(|&(timestamp, _)| timestamp);
+        assert_eq!(by_time.map(|(timestamp, _)| timestamp), Some(5));
+    }
+
+    #[test]
+    fn set_window_keeps_only_most_recent_values() {
+        let mut filter = MovingMedian::<i32, 8>::new();
+        filter.add_values(&[10, 20, 30, 40, 50, 1, 2, 3]);
+        assert_eq!(filter.median(), 15);
+        filter.set_window(3);
+        assert_eq!(filter.effective_len(), 3);
+        assert_eq!(filter.len(), 3);
+        assert_eq!(filter.median(), 2);
+        assert_eq!(filter.iter().copied().collect::<Vec<_>>(), [1, 2, 3]);
+        // The window now wraps at three values
+        assert_eq!(filter.add_value(100), Some(1));
+        assert_eq!(filter.median(), 3);
+        filter.add_values(&[7, 8, 9, 10]);
+        assert_eq!(filter.iter().copied().collect::<Vec<_>>(), [8, 9, 10]);
+
+        // Lengthening the window keeps the values and fills up again
+        filter.set_window(5);
+        assert!(!filter.is_full());
+        filter.add_values(&[11, 12]);
+        assert!(filter.is_full());
+        assert_eq!(filter.add_value(13), Some(8));
+        assert_eq!(filter.capacity(), 8);
+    }
+
+    #[test]
+    fn set_window_is_clamped_to_buffer_size() {
+        let mut filter = MovingMedian::<f64, 4>::new();
+        filter.add_values(&[1.0, 2.0, 3.0]);
+        filter.set_window(0);
+        assert_eq!(filter.effective_len(), 1);
+        assert_eq!(filter.median(), 3.0);
+        filter.set_window(100);
+        assert_eq!(filter.effective_len(), 4);
+        filter.reset_to(2.0);
+        assert_eq!(filter.len(), 4);
+    }
+
+    #[test]
+    fn peek_oldest_and_newest_around_wrap_boundary() {
+        let mut filter = MovingMedian::<u32, 4>::new();
+        for value in 1..=12 {
+            filter.add_value(value);
+            // Just before, at and just after each wrap of the index
+            assert_eq!(filter.peek_newest(), Some(value));
+            assert_eq!(filter.peek_oldest(), Some(value.saturating_sub(3).max(1)));
+        }
+        // A shortened window wraps earlier
+        filter.set_window(3);
+        assert_eq!(filter.peek_oldest(), Some(10));
+        filter.add_value(13);
+        assert_eq!(filter.peek_oldest(), Some(11));
+        assert_eq!(filter.peek_newest(), Some(13));
+        filter.clear();
+        assert_eq!(filter.peek_oldest(), None);
+        assert_eq!(filter.peek_newest(), None);
+    }
+
+    #[test]
+    fn pop_oldest_interleaved_with_add_matches_reference() {
+        let mut rng = XorShift(0x6c8e_9cf5_7093_2bd5);
+        let mut filter = MovingMedian::<i32, 6>::new();
+        let mut window = std::collections::VecDeque::new();
+        for _ in 0..10_000 {
+            // Now and then pop more often than add, so the window also drains to empty
+            let pop_bias = if rng.next() % 100 < 10 { 2 } else { 7 };
+            if rng.next() % 10 < pop_bias {
+                let value = (rng.next() % 50) as i32;
+                let evicted = (window.len() == 6).then(|| window.pop_front().unwrap());
+                window.push_back(value);
+                assert_eq!(filter.add_value(value), evicted);
+            } else {
+                assert_eq!(filter.pop_oldest(), window.pop_front());
+            }
+            let values: Vec<i32> = window.iter().copied().collect();
+            assert_eq!(filter.iter().copied().collect::<Vec<_>>(), values);
+            assert_eq!(filter.len(), window.len());
+            assert_eq!(filter.try_median(), median_of(&values));
+        }
+    }
+
+    #[test]
+    fn pop_oldest_keeps_nan_bookkeeping() {
+        let mut filter = MovingMedian::<f64, 3>::new();
+        filter.add_values(&[f64::NAN, 4.0, 2.0]);
+        assert!(filter.pop_oldest().unwrap().is_nan());
+        assert_eq!(filter.median(), 3.0);
+        filter.add_values(&[8.0, 6.0]);
+        assert_eq!(filter.iter_sorted().collect::<Vec<_>>(), [2.0, 6.0, 8.0]);
+        assert_eq!(filter.median(), 6.0);
+    }
+
+    #[test]
+    fn even_median_does_not_overflow_near_integer_limits() {
+        let mut filter = MovingMedian::<i32, 2>::new();
+        filter.add_values(&[i32::MAX, i32::MAX - 1]);
+        assert_eq!(filter.median(), i32::MAX - 1);
+        assert_eq!(filter.median_ceil(), Some(i32::MAX));
+
+        filter.add_values(&[i32::MIN, i32::MIN + 1]);
+        assert_eq!(filter.median(), i32::MIN);
+        assert_eq!(filter.median_ceil(), Some(i32::MIN + 1));
+
+        filter.add_values(&[i32::MIN, i32::MAX]);
+        assert_eq!(filter.median(), -1);
+        assert_eq!(filter.median_ceil(), Some(0));
+
+        let mut filter = MovingMedian::<u64, 2>::new();
+        filter.add_values(&[u64::MAX, u64::MAX - 1]);
+        assert_eq!(filter.median(), u64::MAX - 1);
+        assert_eq!(filter.median_ceil(), Some(u64::MAX));
+    }
+
+    #[test]
+    fn even_median_does_not_overflow_near_float_limits() {
+        let mut filter = MovingMedian::<f32, 2>::new();
+        filter.add_values(&[f32::MAX, f32::MAX]);
+        assert_eq!(filter.median(), f32::MAX);
+        filter.add_values(&[f32::MAX, f32::MAX / 2.0]);
+        assert_eq!(filter.median(), f32::MAX * 0.75);
+        filter.add_values(&[f32::MIN, f32::MIN]);
+        assert_eq!(filter.median(), f32::MIN);
+        filter.add_values(&[f32::MIN, f32::MAX]);
+        assert_eq!(filter.median(), 0.0);
+        filter.add_values(&[f32::INFINITY, f32::MAX]);
+        assert_eq!(filter.median(), f32::INFINITY);
+
+        let mut filter = MovingMedian::<f64, 2>::new();
+        filter.add_values(&[f64::MAX, f64::MAX]);
+        assert_eq!(filter.median(), f64::MAX);
+        filter.add_values(&[f64::MIN, -f64::MAX / 4.0]);
+        assert_eq!(filter.median(), f64::MIN * 0.625);
+        // Small values are still averaged exactly
+        filter.add_values(&[f64::MIN_POSITIVE, f64::MIN_POSITIVE * 3.0]);
+        assert_eq!(filter.median(), f64::MIN_POSITIVE * 2.0);
+    }
+
+    #[test]
+    fn interpolation_does_not_overflow_at_128_bit_limits() {
+        let filter = MovingMedian::<i128, 2>::from([i128::MIN, i128::MAX]);
+        assert_eq!(filter.quantile(0.0, Interpolation::Linear), Some(i128::MIN));
+        assert_eq!(filter.quantile(1.0, Interpolation::Linear), Some(i128::MAX));
+        let lower = filter.quantile(0.3, Interpolation::Linear).unwrap();
+        assert!((lower as f64 - -0.4 * i128::MAX as f64).abs() < 1e25);
+        assert!(filter.percentile(50.0).unwrap().abs() < 1 << 80);
+        assert_eq!(filter.iqr(), Some(i128::MAX));
+
+        let filter = MovingMedian::<u128, 3>::from([0, u128::MAX, u128::MAX - 1]);
+        assert_eq!(filter.quantile(1.0, Interpolation::Linear), Some(u128::MAX));
+        assert_eq!(
+            filter.quantile(0.75, Interpolation::Linear),
+            Some(u128::MAX - 1)
+        );
+        let middle = filter.quantile(0.25, Interpolation::Linear).unwrap();
+        assert!((middle as f64 - u128::MAX as f64 / 2.0).abs() < 1e25);
+
+        // Exact results for small types are unchanged
+        assert_eq!(Sample::lerp(-7i8, 8, 0.5), 0);
+        assert_eq!(Sample::lerp(i8::MIN, i8::MAX, 1.0), i8::MAX);
+        assert_eq!(Sample::lerp(-3i64, -2, 0.5), -3);
+        assert_eq!(Sample::lerp(250u8, 255, 0.99), 254);
+    }
+
+    #[test]
+    fn replace_and_undo_last_around_wrap_boundary() {
+        let mut rng = XorShift(0x3c6e_f372_fe94_f82b);
+        let mut filter = MovingMedian::<i32, 5>::new();
+        let mut window = std::collections::VecDeque::new();
+        for value in 0..40 {
+            // Fill up to just after the index wraps, then correct the newest value
+            filter.add_value(value);
+            window.push_back(value);
+            if window.len() > 5 {
+                window.pop_front();
+            }
+            let replacement = (rng.next() % 100) as i32;
+            assert_eq!(filter.replace_last(replacement), Some(value));
+            *window.back_mut().unwrap() = replacement;
+            let values: Vec<i32> = window.iter().copied().collect();
+            assert_eq!(filter.try_median(), median_of(&values));
+
+            if rng.next().is_multiple_of(3) {
+                assert_eq!(filter.undo_last(), window.pop_back());
+                let values: Vec<i32> = window.iter().copied().collect();
+                assert_eq!(filter.iter().copied().collect::<Vec<_>>(), values);
+                assert_eq!(filter.try_median(), median_of(&values));
+            }
+        }
+        while !window.is_empty() {
+            assert_eq!(filter.undo_last(), window.pop_back());
+        }
+        assert_eq!(filter.undo_last(), None);
+        assert_eq!(filter.replace_last(1), None);
+        assert!(filter.is_empty());
+        // The index was stepped back, so adding starts over cleanly
+        filter.add_values(&[3, 1, 2]);
+        assert_eq!(filter.iter().copied().collect::<Vec<_>>(), [3, 1, 2]);
+        assert_eq!(filter.median(), 2);
+    }
+
+    #[test]
+    fn position_advances_and_wraps() {
+        let mut filter = MovingMedian::<u8, 4>::new();
+        assert_eq!(filter.position(), 0);
+        for (value, position) in [1, 2, 3, 0, 1, 2, 3, 0, 1].into_iter().enumerate() {
+            filter.add_value(value as u8);
+            assert_eq!(filter.position(), position);
+        }
+        // The oldest value is the one the next write replaces
+        assert_eq!(filter.peek_oldest(), Some(5));
+        filter.clear();
+        assert_eq!(filter.position(), 0);
+    }
+
+    #[test]
+    fn parts_round_trip_wrapped_buffer() {
+        let mut filter = MovingMedian::<f32, 5>::new();
+        filter.add_values(&[1.0, 9.0, f32::NAN, 4.0, 7.0, 3.0, 8.0]);
+        let (buffer, index, count) = filter.to_parts();
+        assert_eq!((index, count), (2, 5));
+        let mut restored = MovingMedian::from_parts(buffer, index, count).unwrap();
+        assert_eq!(std::format!("{:?}", restored), std::format!("{:?}", filter));
+        assert_eq!(restored.median(), filter.median());
+        // Adding continues the ring where the original left off
+        assert_eq!(
+            restored.add_value(2.0).map(f32::to_bits),
+            filter.add_value(2.0).map(f32::to_bits)
+        );
+        assert_eq!(
+            restored.iter_sorted().collect::<Vec<_>>(),
+            filter.iter_sorted().collect::<Vec<_>>()
+        );
+
+        let mut partial = MovingMedian::<i64, 4>::new();
+        partial.add_values(&[6, -2]);
+        let (buffer, index, count) = partial.to_parts();
+        assert_eq!(
+            MovingMedian::from_parts(buffer, index, count).unwrap(),
+            partial
+        );
+    }
+
+    #[test]
+    fn replay_from_snapshot_of_warmed_filter_is_deterministic() {
+        let mut rng = XorShift(0x2545_f491_4f6c_dd1d);
+        let mut filter = MovingMedian::<i32, 7>::new();
+        for _ in 0..20 {
+            filter.add_value((rng.next() % 1_000) as i32);
+        }
+        let snapshot = filter.snapshot();
+        assert_eq!(snapshot.count, 7);
+        assert_eq!(snapshot.index, filter.position());
+        assert_eq!(
+            (snapshot.buffer, snapshot.index, snapshot.count),
+            filter.to_parts()
+        );
+        let mut replay = snapshot.into_filter().unwrap();
+        assert_eq!(replay.median(), filter.median());
+        for _ in 0..200 {
+            let value = (rng.next() % 1_000) as i32;
+            assert_eq!(replay.add_value(value), filter.add_value(value));
+            assert_eq!(replay.median(), filter.median());
+            assert_eq!(replay.position(), filter.position());
+        }
+
+        let mut corrupt = replay.snapshot();
+        corrupt.index = 7;
+        assert_eq!(corrupt.into_filter(), Err(InvalidState::Index(7)));
+    }
+
+    #[test]
+    fn replay_from_snapshot_keeps_window_and_policies() {
+        let mut rng = XorShift(0x9e37_79b9_7f4a_7c15);
+        let mut filter = MovingMedian::<f64, 6>::new();
+        filter.set_nan_policy(NanPolicy::Propagate);
+        filter.set_even_strategy(EvenStrategy::TakeLower);
+        filter.clear_with_default(-1.0);
+        filter.add_values(&[4.0, 8.0, 1.0, 5.0]);
+        filter.set_window(3);
+        let snapshot = filter.snapshot();
+        assert_eq!(snapshot.effective_len, 3);
+        assert_eq!(snapshot.empty_median, Some(-1.0));
+        let mut replay = snapshot.into_filter().unwrap();
+        assert_eq!(replay.nan_policy(), NanPolicy::Propagate);
+        assert_eq!(replay.even_strategy(), EvenStrategy::TakeLower);
+        assert_eq!(replay.total_pushed(), filter.total_pushed());
+        for _ in 0..100 {
+            let value = match rng.next() % 16 {
+                0 => f64::NAN,
+                value => value as f64,
+            };
+            assert_eq!(
+                replay.add_value(value).map(f64::to_bits),
+                filter.add_value(value).map(f64::to_bits)
+            );
+            assert_eq!(replay.median().to_bits(), filter.median().to_bits());
+            assert_eq!(replay.len(), filter.len());
+        }
+        assert_eq!(replay.total_pushed(), filter.total_pushed());
+        while replay.pop_oldest().is_some() {}
+        assert_eq!(replay.median(), -1.0);
+
+        let mut corrupt = filter.snapshot();
+        corrupt.effective_len = 7;
+        assert_eq!(corrupt.into_filter(), Err(InvalidState::Window(7)));
+        corrupt.effective_len = 3;
+        corrupt.count = 4;
+        assert_eq!(corrupt.into_filter(), Err(InvalidState::Count(4)));
+    }
+
+    #[test]
+    fn parts_of_shortened_window_restore_with_set_window() {
+        let mut filter = MovingMedian::<i32, 6>::new();
+        filter.set_window(3);
+        filter.add_values(&[1, 2, 3, 4, 5]);
+        let (buffer, index, count) = filter.to_parts();
+        let mut restored = MovingMedian::from_parts(buffer, index, count).unwrap();
+        restored.set_window(3);
+        assert_eq!(restored, filter);
+        assert_eq!(restored.add_value(10), filter.add_value(10));
+    }
+
+    #[test]
+    fn from_parts_rejects_invalid_state() {
+        assert_eq!(
+            MovingMedian::<u8, 3>::from_parts([0; 3], 3, 0),
+            Err(InvalidState::Index(3))
+        );
+        assert_eq!(
+            MovingMedian::<u8, 3>::from_parts([0; 3], 0, 4),
+            Err(InvalidState::Count(4))
+        );
+        assert_eq!(
+            std::format!("{}", InvalidState::Count(4)),
+            "count 4 is greater than the buffer size"
+        );
+    }
+
+    #[test]
+    fn from_slice_keeps_the_last_n_values_in_order() {
+        let mut filter = MovingMedian::<u32, 4>::from_slice(&[1, 2, 3, 4, 5, 6]);
+        assert_eq!(filter.iter().copied().collect::<Vec<_>>(), [3, 4, 5, 6]);
+        assert_eq!(filter.peek_oldest(), Some(3));
+        assert_eq!(filter.peek_newest(), Some(6));
+
+        let mut pushed = MovingMedian::<u32, 4>::new();
+        for value in 1..=6 {
+            pushed.add_value(value);
+        }
+        assert_eq!(filter.position(), pushed.position());
+        assert_eq!(filter.add_value(7), pushed.add_value(7));
+        assert_eq!(filter, pushed);
+
+        let short = MovingMedian::<u32, 4>::from_slice(&[8, 9]);
+        assert_eq!(short.iter().copied().collect::<Vec<_>>(), [8, 9]);
+        assert!(!short.is_full());
+    }
+
+    #[test]
+    fn median_and_mean_match_individual_methods() {
+        let mut filter = MovingMedian::<i32, 7>::new();
+        assert_eq!(filter.median_and_mean(), None);
+        // A skewed window, where the mean is pulled far away from the median
+        filter.add_values(&[2, 1, 3, 2, 250, 4, 900]);
+        assert_eq!(filter.median_and_mean(), Some((3, 166)));
+        assert_eq!(
+            filter.median_and_mean(),
+            Some((filter.median(), filter.mean()))
+        );
+
+        let mut filter = MovingMedian::<f64, 3>::new();
+        filter.add_values(&[1.0, f64::NAN, 5.0]);
+        assert_eq!(filter.median_and_mean(), Some((3.0, 3.0)));
+        filter.set_nan_policy(NanPolicy::Propagate);
+        let (median, mean) = filter.median_and_mean().unwrap();
+        assert!(median.is_nan() && mean.is_nan());
+    }
+
+    #[test]
+    fn trimmed_mean_fraction_drops_extremes() {
+        let mut filter = MovingMedian::<f64, 5>::new();
+        assert_eq!(filter.trimmed_mean_fraction(0.25), None);
+        filter.add_values(&[1.0, 2.0, 3.0, 4.0, 100.0]);
+        assert_eq!(filter.trimmed_mean_fraction(0.0), Some(filter.mean()));
+        assert_eq!(filter.trimmed_mean_fraction(-1.0), Some(filter.mean()));
+        // floor(5 * 0.25) = 1 value is dropped at each end, so 1 and 100 are left out
+        assert_eq!(filter.trimmed_mean_fraction(0.25), Some(3.0));
+        assert_eq!(filter.trimmed_mean_fraction(0.4), Some(3.0));
+        assert_eq!(filter.trimmed_mean_fraction(1.0), Some(3.0));
+        // At least one value remains, and for an even number of values two
+        let filter = MovingMedian::<f64, 6>::from([1.0, 2.0, 3.0, 4.0, 5.0, 100.0]);
+        assert_eq!(filter.trimmed_mean_fraction(0.5), Some(3.5));
+    }
+
+    #[test]
+    fn total_pushed_keeps_counting_once_full() {
+        let mut filter = MovingMedian::<i32, 3>::new();
+        for value in 0..10 {
+            filter.add_value(value);
+        }
+        assert_eq!(filter.len(), 3);
+        assert_eq!(filter.total_pushed(), 10);
+        filter.add_values(&[1, 2, 3, 4]);
+        assert_eq!(filter.total_pushed(), 14);
+        filter.add_values(&[5]);
+        filter.pop_oldest();
+        assert_eq!(filter.total_pushed(), 15);
+        filter.clear();
+        assert_eq!(filter.total_pushed(), 0);
+    }
+
+    #[test]
+    fn clear_with_default_sets_empty_median() {
+        let mut filter = MovingMedian::<i32, 3>::new();
+        filter.add_values(&[1, 2, 3]);
+        filter.clear_with_default(-40);
+        assert!(filter.is_empty());
+        assert_eq!(filter.median(), -40);
+        // Calling it again is the same as calling it once
+        filter.clear_with_default(-40);
+        assert_eq!(filter.median(), -40);
+        assert_eq!(filter.total_pushed(), 0);
+
+        filter.add_value(7);
+        assert_eq!(filter.median(), 7);
+        // The default stays until the filter is cleared
+        filter.pop_oldest();
+        assert_eq!(filter.median(), -40);
+        filter.clear();
+        assert_eq!(filter.median(), 0);
+
+        let mut filter = MovingMedian::<f64, 2>::new();
+        filter.clear_with_default(1.5);
+        filter.add_value(f64::NAN);
+        assert_eq!(filter.median(), 1.5);
+    }
+
+    #[test]
+    fn map_keeps_window_and_converts_median() {
+        let volts = |count: u16| count as f32 * 3.3 / 4095.0;
+        let mut counts = MovingMedian::<u16, 5>::new();
+        counts.add_values(&[100, 4000, 2000, 3000, 50, 1000]);
+        let mapped = counts.map(volts);
+        // A rising conversion keeps the middle value in the middle
+        assert_eq!(mapped.median(), volts(counts.median()));
+        assert_eq!(
+            mapped.iter().copied().collect::<Vec<_>>(),
+            counts.iter().map(|&count| volts(count)).collect::<Vec<_>>()
+        );
+        assert_eq!(mapped.position(), counts.position());
+        assert_eq!(mapped.len(), 5);
+
+        // A falling conversion reverses the order, and the sorted buffer follows
+        let mut odd = MovingMedian::<u16, 3>::from([1, 5, 3]);
+        let mut negated = odd.map(|count| -(count as i32));
+        assert_eq!(negated.iter_sorted().collect::<Vec<_>>(), [-5, -3, -1]);
+        assert_eq!(negated.median(), -3);
+        // The mapped filter continues the ring like the original
+        assert_eq!(odd.add_value(7), Some(1));
+        assert_eq!(negated.add_value(-7), Some(-1));
+        assert_eq!(negated.median(), -5);
+    }
+
+    #[test]
+    fn recomputed_and_running_means_match_exact_window_mean() {
+        let mut rng = XorShift(0xd1b5_4a32_d192_ed03);
+        let mut filter = MovingMedian::<f32, 32>::new();
+        let mut running = MovingMean::<f32, 32>::new();
+        let mut window = std::collections::VecDeque::new();
+        assert_eq!(filter.try_mean(), None);
+        for i in 0..50_000 {
+            // Mix large and small magnitudes, which makes a running sum without resync drift
+            let scale = if i % 7 == 0 { 1e6 } else { 1e-2 };
+            let value = (rng.next() % 1_000) as f32 * scale;
+            filter.add_value(value);
+            running.add_value(value);
+            window.push_back(value as f64);
+            if window.len() > 32 {
+                window.pop_front();
+            }
+            let expected = window.iter().sum::<f64>() / window.len() as f64;
+            let tolerance = expected.abs() * 1e-6 + 1e-6;
+            assert!((filter.mean() as f64 - expected).abs() <= tolerance);
+            assert!((running.mean() as f64 - expected).abs() <= tolerance);
+        }
+        // Huge values round away the small ones from an f64 running sum, until it is summed again
+        let mut filter = MovingMedian::<f64, 8>::new();
+        let mut window = std::collections::VecDeque::new();
+        for i in 0..50_000 {
+            let value = match rng.next() % 50 {
+                0 => 1e17,
+                value => value as f64 + 0.25,
+            };
+            filter.add_value(value);
+            window.push_back(value);
+            if window.len() > 8 {
+                window.pop_front();
+            }
+            if filter.position() == 0 || i < 8 {
+                let expected = window.iter().sum::<f64>() / window.len() as f64;
+                let largest = window.iter().fold(0.0f64, |max, value| max.max(*value));
+                assert!((filter.mean() - expected).abs() <= largest * 1e-12);
+            }
+        }
+        // Large u16 values cannot overflow the sum
+        let filter = MovingMedian::<u16, 4>::from([u16::MAX; 4]);
+        assert_eq!(filter.try_mean(), Some(u16::MAX));
+    }
+
+    #[test]
+    fn running_sum_follows_every_change_of_the_window() {
+        // Sum the ordered values of the window from scratch
+        fn recomputed<const N: usize>(filter: &MovingMedian<i64, N>) -> Option<i64> {
+            let values = filter.iter().collect::<Vec<_>>();
+            let sum = values.iter().map(|&&value| value as i128).sum::<i128>();
+            (!values.is_empty()).then(|| sum.div_euclid(values.len() as i128) as i64)
+        }
+
+        let mut rng = XorShift(0x6a09_e667_f3bc_c909);
+        let mut filter = MovingMedian::<i64, 12>::new();
+        for _ in 0..20_000 {
+            let value = (rng.next() % 2_000) as i64 - 1_000;
+            match rng.next() % 16 {
+                0 => {
+                    filter.pop_oldest();
+                }
+                1 => {
+                    filter.undo_last();
+                }
+                2 => {
+                    filter.replace_last(value);
+                }
+                3 => filter.set_window(1 + (rng.next() % 12) as usize),
+                4 => filter.retain(|&kept| kept % 3 != 0),
+                5 if rng.next().is_multiple_of(8) => filter.clear(),
+                6 if rng.next().is_multiple_of(8) => filter.reset_to(value),
+                7 => filter.add_values(&[value; 13]),
+                _ => {
+                    filter.add_value(value);
+                }
+            }
+            assert_eq!(filter.try_mean(), recomputed(&filter));
+        }
+
+        let (buffer, index, count) = filter.to_parts();
+        let restored = MovingMedian::<i64, 12>::from_parts(buffer, index, count).unwrap();
+        assert_eq!(restored.try_mean(), recomputed(&restored));
+        let restored = filter.snapshot().into_filter().unwrap();
+        assert_eq!(restored.try_mean(), filter.try_mean());
+        let mapped = filter.map(|value| value * 2);
+        assert_eq!(mapped.try_mean(), recomputed(&mapped));
+    }
+
+    #[test]
+    fn running_sum_leaves_nan_out_and_survives_128_bit_limits() {
+        let mut filter = MovingMedian::<f32, 3>::new();
+        filter.add_values(&[f32::NAN, 2.0, f32::NAN]);
+        assert_eq!(filter.try_mean(), Some(2.0));
+        filter.pop_oldest();
+        filter.pop_oldest();
+        assert_eq!(filter.try_mean(), None);
+        filter.add_value(4.0);
+        assert_eq!(filter.try_mean(), Some(4.0));
+
+        // The running sum wraps around in between, and is exact again once the sum fits
+        let mut filter = MovingMedian::<i128, 2>::new();
+        filter.add_values(&[i128::MAX, i128::MAX]);
+        filter.add_value(i128::MIN);
+        assert_eq!(filter.try_mean(), Some(-1));
+        let mut filter = MovingMedian::<u128, 2>::new();
+        filter.add_values(&[u128::MAX, u128::MAX]);
+        filter.add_value(1);
+        assert_eq!(filter.undo_last(), Some(1));
+        assert_eq!(filter.try_mean(), Some(u128::MAX));
+    }
+
+    #[test]
+    fn decayed_median_moves_from_plain_median_to_newest_value() {
+        let mut rng = XorShift(0x94d0_49bb_1331_11eb);
+        let mut filter = MovingMedian::<i32, 9>::new();
+        assert_eq!(filter.decayed_median(0.5), None);
+        for _ in 0..1_000 {
+            let value = (rng.next() % 100) as i32;
+            filter.add_value(value);
+            // Equal weights give the plain median, and nearly equal weights stay close to it
+            assert_eq!(filter.decayed_median(1.0), filter.try_median());
+            let near = filter.decayed_median(0.999).unwrap();
+            assert!(filter.median_low().unwrap() <= near && near <= filter.median_high().unwrap());
+            // The newest value outweighs all others together
+            assert_eq!(filter.decayed_median(0.1), Some(value));
+        }
+    }
+
+    #[test]
+    fn decayed_median_skips_nan_but_ages_past_it() {
+        let mut filter = MovingMedian::<f64, 4>::new();
+        filter.add_values(&[5.0, 1.0, f64::NAN, 3.0]);
+        // 3 has a weight of 1, NaN would have 0.5 if it took part, 1 has 0.25 and 5 has 0.125
+        assert_eq!(filter.decayed_median(0.5), Some(3.0));
+        filter.add_value(f64::NAN);
+        // 3 now weighs 0.5, 1 weighs 0.125, so 3 still holds more than half of 0.625
+        assert_eq!(filter.decayed_median(0.5), Some(3.0));
+        filter.set_nan_policy(NanPolicy::Propagate);
+        assert!(filter.decayed_median(0.5).unwrap().is_nan());
+    }
+
+    #[test]
+    fn retain_purges_sentinels_and_keeps_order() {
+        let mut filter = MovingMedian::<i32, 5>::new();
+        filter.add_values(&[7, 9999, 1, 9999, 4, 9999, 3]);
+        // The window wrapped around and holds 1, 9999, 4, 9999 and 3
+        filter.retain(|&value| value != 9999);
+        assert_eq!(filter.len(), 3);
+        assert_eq!(filter.iter().copied().collect::<Vec<_>>(), [1, 4, 3]);
+        assert_eq!(filter.median(), 3);
+        assert_eq!(filter.to_parts(), ([1, 4, 3, 0, 0], 3, 3));
+        // New values fill the window up again before the oldest is evicted
+        assert_eq!(filter.add_value(8), None);
+        assert_eq!(filter.add_value(2), None);
+        assert_eq!(filter.add_value(6), Some(1));
+        assert_eq!(filter.median(), 4);
+
+        filter.retain(|_| false);
+        assert!(filter.is_empty());
+        assert_eq!(filter.try_median(), None);
+    }
+
+    #[test]
+    fn iqr_of_small_windows() {
+        let mut filter = MovingMedian::<f64, 5>::new();
+        assert_eq!(filter.iqr(), None);
+        filter.add_value(7.0);
+        assert_eq!(filter.iqr(), Some(0.0));
+        // The quartiles fall between the two values, at a quarter and three quarters
+        filter.add_value(9.0);
+        assert_eq!(filter.iqr(), Some(1.0));
+        // Ranks 0.5 and 1.5
+        filter.add_value(8.0);
+        assert_eq!(filter.iqr(), Some(1.0));
+        // Ranks 0.75 and 2.25
+        filter.add_value(20.0);
+        assert_eq!(filter.iqr(), Some(11.75 - 7.75));
+        // Ranks 1 and 3 hit values exactly
+        filter.add_value(0.0);
+        assert_eq!(filter.iqr(), Some(2.0));
+
+        let filter = MovingMedian::<i32, 4>::from([1, 2, 3, 4]);
+        // The quartiles 1.75 and 3.25 are rounded down to 1 and 3
+        assert_eq!(filter.iqr(), Some(2));
+        assert_eq!(filter.range(), Some(3));
+    }
+
+    #[test]
+    fn copy_window_is_contiguous_after_wrap() {
+        let mut filter = MovingMedian::<i16, 4>::new();
+        let mut out = [-1; 6];
+        assert_eq!(filter.copy_window(&mut []), 0);
+        filter.add_values(&[1, 2, 3, 4, 5, 6]);
+        assert_eq!(filter.copy_window(&mut out), 4);
+        assert_eq!(out, [3, 4, 5, 6, -1, -1]);
+        filter.pop_oldest();
+        assert_eq!(filter.copy_window(&mut out[..3]), 3);
+        assert_eq!(out[..3], [4, 5, 6]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn copy_window_panics_on_short_output() {
+        let filter = MovingMedian::<u8, 3>::from([1, 2, 3]);
+        filter.copy_window(&mut [0; 2]);
+    }
+
+    #[test]
+    fn is_iqr_outlier_flags_spikes_outside_fences() {
+        let mut filter = MovingMedian::<f64, 8>::new();
+        filter.add_values(&[20.0, 21.0, 19.5]);
+        // Too few values to judge
+        assert!(!filter.is_iqr_outlier(1_000.0, 1.5));
+        filter.add_values(&[20.5, 20.0, 21.5, 19.0, 20.0]);
+        // Quartiles 19.875 and 20.625, so the fences are at 18.75 and 21.75
+        assert!(!filter.is_iqr_outlier(21.75, 1.5));
+        assert!(!filter.is_iqr_outlier(18.75, 1.5));
+        assert!(filter.is_iqr_outlier(21.8, 1.5));
+        assert!(filter.is_iqr_outlier(-5.0, 1.5));
+        assert!(!filter.is_iqr_outlier(22.5, 3.0));
+        assert!(filter.is_iqr_outlier(23.0, 3.0));
+        assert!(!filter.is_iqr_outlier(f64::NAN, 1.5));
+        // The check does not change the filter
+        assert_eq!(filter.len(), 8);
+        assert_eq!(filter.median(), 20.0);
+    }
+
+    #[test]
+    fn is_iqr_outlier_of_constant_window() {
+        let filter = MovingMedian::<u16, 6>::from([500; 6]);
+        assert_eq!(filter.iqr(), Some(0));
+        assert!(!filter.is_iqr_outlier(500, 3));
+        assert!(filter.is_iqr_outlier(501, 3));
+        assert!(filter.is_iqr_outlier(499, 3));
+        // NaN values do not count towards the values needed to judge
+        let mut filter = MovingMedian::<f32, 6>::new();
+        filter.add_values(&[1.0, 1.0, 1.0, f32::NAN]);
+        assert!(!filter.is_iqr_outlier(9.0, 1.5));
+        filter.add_value(1.0);
+        assert!(filter.is_iqr_outlier(9.0, 1.5));
+    }
+
+    #[test]
+    fn index_follows_window_order_across_wrap() {
+        let mut filter = MovingMedian::<u32, 4>::new();
+        filter.add_values(&[10, 20]);
+        assert_eq!((filter[0], filter[1]), (10, 20));
+        filter.add_values(&[30, 40, 50, 60]);
+        // The oldest value is now stored in the middle of the buffer
+        assert_eq!(filter.position(), 2);
+        assert_eq!(
+            [filter[0], filter[1], filter[2], filter[3]],
+            [30, 40, 50, 60]
+        );
+        assert_eq!(Some(filter[filter.len() - 1]), filter.peek_newest());
+        assert_eq!(Some(filter[0]), filter.peek_oldest());
+
+        filter.set_window(3);
+        filter.add_value(70);
+        assert_eq!([filter[0], filter[1], filter[2]], [50, 60, 70]);
+    }
+
+    #[test]
+    #[should_panic(expected = "the len is 2 but the index is 2")]
+    fn index_past_len_panics() {
+        let filter = MovingMedian::<f32, 4>::from_slice(&[1.0, 2.0]);
+        let _ = filter[2];
+    }
+
+    #[test]
+    fn ref_into_iterator_walks_window_chronologically() {
+        let mut filter = MovingMedian::<i8, 3>::new();
+        filter.add_values(&[1, 2, 3, 4, 5]);
+        let mut values = Vec::new();
+        for &value in &filter {
+            values.push(value);
+        }
+        assert_eq!(values, [3, 4, 5]);
+
+        let mut iter = filter.iter();
+        assert_eq!(iter.len(), 3);
+        assert_eq!(iter.next_back(), Some(&5));
+        assert_eq!(iter.next(), Some(&3));
+        assert_eq!(iter.len(), 1);
+        assert_eq!(iter.next_back(), Some(&4));
+        assert_eq!(iter.next(), None);
+        assert_eq!(iter.next_back(), None);
+    }
+
+    #[test]
+    fn values_erase_the_ring_rotation() {
+        let mut filter = MovingMedian::<i64, 5>::new();
+        assert_eq!(filter.values(), ([0; 5], 0));
+        // A partial window fills the start and leaves zeros after it
+        filter.add_values(&[-3, 8, 1]);
+        assert_eq!(filter.values(), ([-3, 8, 1, 0, 0], 3));
+        // Once wrapped, the oldest value is stored at position 2 but comes first
+        filter.add_values(&[6, 2, 9, 7]);
+        assert_eq!(filter.position(), 2);
+        assert_eq!(filter.to_parts().0, [9, 7, 1, 6, 2]);
+        assert_eq!(filter.values(), ([1, 6, 2, 9, 7], 5));
+        filter.set_window(2);
+        assert_eq!(filter.values(), ([9, 7, 0, 0, 0], 2));
+    }
+
+    #[test]
+    fn median_unchecked_matches_median() {
+        let mut rng = XorShift(0x2545_f491_4f6c_dd1d);
+        for strategy in [
+            EvenStrategy::AverageMiddle,
+            EvenStrategy::TakeLower,
+            EvenStrategy::TakeUpper,
+        ] {
+            let mut filter = MovingMedian::<i32, 6>::new();
+            filter.set_even_strategy(strategy);
+            for _ in 0..500 {
+                filter.add_value((rng.next() % 100) as i32 - 50);
+                assert_eq!(filter.median_unchecked(), filter.median());
+            }
+        }
+
+        let mut filter = MovingMedian::<f32, 3>::new();
+        filter.add_values(&[f32::NAN, 1.0]);
+        assert_eq!(filter.median_unchecked(), 1.0);
+        filter.set_nan_policy(NanPolicy::Propagate);
+        assert!(filter.median_unchecked().is_nan());
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "without a median")]
+    fn median_unchecked_of_empty_filter_panics_in_debug() {
+        let mut filter = MovingMedian::<u8, 3>::new();
+        filter.add_value(1);
+        filter.clear();
+        filter.median_unchecked();
+    }
+
+    #[test]
+    fn median_full_only_waits_for_nth_value() {
+        let mut filter = MovingMedian::<u16, 4>::new();
+        for (i, value) in [40, 10, 30, 20, 50].into_iter().enumerate() {
+            filter.add_value(value);
+            // Partial windows keep their median
+            assert!(filter.try_median().is_some());
+            assert_eq!(filter.median_full_only().is_some(), i >= 3);
+            assert_eq!(filter.is_warmed_up(), i >= 3);
+        }
+        assert_eq!(filter.median_full_only(), Some(25));
+
+        // The warm-up starts over after clearing
+        filter.clear();
+        assert!(!filter.is_warmed_up());
+        filter.add_values(&[1, 2, 3]);
+        assert!(!filter.is_warmed_up());
+        assert_eq!(filter.median_full_only(), None);
+        filter.add_value(4);
+        assert!(filter.is_warmed_up());
+        assert_eq!(filter.median_full_only(), Some(2));
+        filter.clear();
+        filter.add_values(&[1, 2, 3]);
+        assert_eq!(filter.median(), 2);
+        filter.set_window(2);
+        assert_eq!(filter.median_full_only(), Some(2));
+        filter.set_window(4);
+        assert_eq!(filter.median_full_only(), None);
+    }
+
+    #[test]
+    fn repeated_median_calls_agree_between_adds() {
+        let mut rng = XorShift(0x2545_f491_4f6c_dd1d);
+        let mut filter = MovingMedian::<f64, 9>::new();
+        for _ in 0..300 {
+            filter.add_value((rng.next() % 64) as f64);
+            let sorted = filter.iter_sorted().collect::<Vec<_>>();
+            let median = filter.median();
+            for _ in 0..3 {
+                assert_eq!(filter.median().to_bits(), median.to_bits());
+                assert_eq!(filter.try_median(), Some(median));
+            }
+            // Querying the median leaves the sorted buffer as it was
+            assert_eq!(filter.iter_sorted().collect::<Vec<_>>(), sorted);
+        }
+        filter.clear();
+        assert_eq!(filter.median(), 0.0);
+        assert_eq!(filter.median(), 0.0);
+    }
+
+    #[test]
+    fn median_by_key_uses_window_after_pop_undo_and_from_parts() {
+        let mut filter = MovingMedian::<i32, 4>::new();
+        filter.add_values(&[1, 2, 3]);
+        filter.pop_oldest();
+        assert_eq!(filter.median_by_key(|&value| value), Some(2));
+        filter.pop_oldest();
+        assert_eq!(filter.median_by_key(|&value| value), Some(3));
+
+        let mut filter = MovingMedian::<i32, 4>::new();
+        filter.add_values(&[10, 20, 30, 40, 50]);
+        // The window is 20, 30, 40 once the newest value is undone, with 50 left in a stale slot
+        filter.undo_last();
+        assert_eq!(filter.median_by_key(|&value| -value), Some(30));
+        filter.undo_last();
+        assert_eq!(filter.median_by_key(|&value| value), Some(20));
+
+        // Two values stored at the end of the buffer, with stale values in front of them
+        let filter = MovingMedian::<i32, 5>::from_parts([90, 80, 70, 4, 6], 0, 2).unwrap();
+        assert_eq!(filter.median_by_key(|&value| value), Some(4));
+        assert_eq!(filter.median_by_key(|&value| -value), Some(6));
+    }
+
+    #[test]
+    fn newest_and_oldest_around_wrap_and_set_window() {
+        let mut filter = MovingMedian::<u8, 3>::new();
+        assert_eq!((filter.newest(), filter.oldest()), (None, None));
+        filter.add_value(1);
+        assert_eq!((filter.newest(), filter.oldest()), (Some(1), Some(1)));
+        filter.add_values(&[2, 3]);
+        // Full but not wrapped yet, the oldest value is in the first slot
+        assert_eq!(filter.position(), 0);
+        assert_eq!((filter.newest(), filter.oldest()), (Some(3), Some(1)));
+        filter.add_value(4);
+        // Wrapped, the newest value is in the first slot and the oldest follows it
+        assert_eq!(filter.position(), 1);
+        assert_eq!((filter.newest(), filter.oldest()), (Some(4), Some(2)));
+        filter.add_values(&[5, 6]);
+        assert_eq!(filter.position(), 0);
+        assert_eq!((filter.newest(), filter.oldest()), (Some(6), Some(4)));
+
+        filter.set_window(2);
+        assert_eq!((filter.newest(), filter.oldest()), (Some(6), Some(5)));
+        filter.add_value(7);
+        assert_eq!((filter.newest(), filter.oldest()), (Some(7), Some(6)));
+        filter.set_window(3);
+        assert_eq!((filter.newest(), filter.oldest()), (Some(7), Some(6)));
+        filter.add_values(&[8, 9]);
+        assert_eq!((filter.newest(), filter.oldest()), (Some(9), Some(7)));
+
+        filter.clear();
+        assert_eq!((filter.newest(), filter.oldest()), (None, None));
     }
 }
@@ -0,0 +1,210 @@
+//! A moving mean filter with the same window semantics as the moving median.
+
+use crate::Sample;
+
+/// A no-std moving mean filter with a fixed-size buffer of size N.
+///
+/// The window behaves like the one of [`MovingMedian`](crate::MovingMedian): it holds the last N
+/// values and the oldest value is replaced once it is full. A running sum is kept in the wide
+/// [`Sample::Accumulator`] type, so the mean is available without summing the window and integer
+/// sums cannot overflow. Adding and subtracting floats lets rounding errors build up in the sum,
+/// so it is recomputed from the buffer every N evictions, which bounds the drift to the rounding
+/// errors of 2N additions in double precision.
+///
+/// NaN values are kept in the window but left out of the mean, like [`NanPolicy::Ignore`](crate::NanPolicy::Ignore).
+///
+/// ```
+/// use moving_median::MovingMean;
+///
+/// let mut filter = MovingMean::<f32, 3>::new();
+/// filter.add_value(1.0);
+/// filter.add_value(2.0);
+/// filter.add_value(6.0);
+/// filter.add_value(7.0);
+///
+/// assert_eq!(filter.mean(), 5.0);
+/// ```
+#[derive(Clone)]
+pub struct MovingMean<T: Sample, const N: usize> {
+    // The values, kept so the evicted value can be subtracted and the sum recomputed
+    buffer: [T; N],
+    index: usize,
+    count: usize,
+    // Sum of the values in the buffer other than NaN
+    sum: T::Accumulator,
+    // Number of values in the buffer other than NaN
+    summed: usize,
+}
+
+impl<T: Sample, const N: usize> MovingMean<T, N> {
+    /// Create a new moving mean filter with a fixed-size buffer of size N.
+    pub fn new() -> Self {
+        Self {
+            buffer: [T::zero(); N],
+            index: 0,
+            count: 0,
+            sum: T::zero().accumulate(),
+            summed: 0,
+        }
+    }
+
+    /// Add a new measurement to the buffer.
+    /// If the buffer is full, the oldest value will be replaced.
+    /// Returns the replaced oldest value, or `None` while the buffer is still filling up.
+    pub fn add_value(&mut self, value: T) -> Option<T> {
+        let evicted = if self.count == N {
+            let oldest = self.buffer[self.index];
+            if !oldest.is_nan() {
+                self.sum = self.sum - oldest.accumulate();
+                self.summed -= 1;
+            }
+            Some(oldest)
+        } else {
+            self.count += 1;
+            None
+        };
+        if !value.is_nan() {
+            self.sum = self.sum + value.accumulate();
+            self.summed += 1;
+        }
+        self.buffer[self.index] = value;
+        // Move to the next index, wrapping around if necessary
+        self.index = (self.index + 1) % N;
+        // Every value has been evicted once since the last wrap, so start the sum over from the buffer
+        if evicted.is_some() && self.index == 0 {
+            self.recompute_sum();
+        }
+        evicted
+    }
+
+    /// Remove all values from the buffer, so the filter starts over as if it was new.
+    pub fn clear(&mut self) {
+        self.index = 0;
+        self.count = 0;
+        self.sum = T::zero().accumulate();
+        self.summed = 0;
+    }
+
+    /// Number of values in the buffer, at most N. NaN values are included.
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    /// Returns `true` if no values have been added.
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Returns `true` once N values have been added and the buffer has warmed up.
+    pub fn is_full(&self) -> bool {
+        self.count == N
+    }
+
+    /// Size N of the buffer, which is the number of values the filter holds once it is full.
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
+    /// Calculate the arithmetic mean of the values in the buffer.
+    /// For integers the mean is rounded towards negative infinity (see [`Sample::mean`]).
+    /// If no values have been added, zero is returned.
+    pub fn mean(&self) -> T {
+        self.try_mean().unwrap_or(T::zero())
+    }
+
+    /// Calculate the arithmetic mean of the values in the buffer, like [`mean`](Self::mean).
+    /// Returns `None` if the buffer holds no values other than NaN.
+    pub fn try_mean(&self) -> Option<T> {
+        (self.summed > 0).then(|| T::mean(self.sum, self.summed))
+    }
+
+    fn recompute_sum(&mut self) {
+        self.sum = self
+            .buffer
+            .iter()
+            .filter(|value| !value.is_nan())
+            .fold(T::zero().accumulate(), |sum, value| {
+                sum + value.accumulate()
+            });
+    }
+}
+
+impl<T: Sample, const N: usize> Default for MovingMean<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use crate::test_util::XorShift;
+    use std::collections::VecDeque;
+
+    #[test]
+    fn mean_is_none_when_no_values_added() {
+        let filter = MovingMean::<f64, 3>::new();
+        assert_eq!(filter.try_mean(), None);
+        assert_eq!(filter.mean(), 0.0);
+    }
+
+    #[test]
+    fn mean_matches_naive_recompute_for_integers() {
+        let mut rng = XorShift(0x2545_f491_4f6c_dd1d);
+        let mut filter = MovingMean::<i64, 7>::new();
+        let mut window = VecDeque::new();
+        for _ in 0..5_000 {
+            // Large values would overflow a sum in the sample type
+            let value = (rng.next() >> 1) as i64 - i64::MAX / 2;
+            filter.add_value(value);
+            window.push_back(value as i128);
+            if window.len() > 7 {
+                window.pop_front();
+            }
+            let sum: i128 = window.iter().sum();
+            let expected = sum.div_euclid(window.len() as i128) as i64;
+            assert_eq!(filter.mean(), expected);
+        }
+    }
+
+    #[test]
+    fn mean_matches_naive_recompute_for_floats() {
+        let mut rng = XorShift(0x9e37_79b9_7f4a_7c15);
+        let mut filter = MovingMean::<f64, 16>::new();
+        let mut window = VecDeque::new();
+        for i in 0..20_000 {
+            // Mix large and small magnitudes to provoke rounding errors in the running sum
+            let scale = if i % 3 == 0 { 1e12 } else { 1e-3 };
+            let value = (rng.next() % 1000) as f64 * scale;
+            filter.add_value(value);
+            window.push_back(value);
+            if window.len() > 16 {
+                window.pop_front();
+            }
+            let expected = window.iter().sum::<f64>() / window.len() as f64;
+            let error = (filter.mean() - expected).abs();
+            assert!(
+                error <= expected.abs() * 1e-12 + 1e-9,
+                "{} vs {}",
+                filter.mean(),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn nan_is_ignored() {
+        let mut filter = MovingMean::<f32, 3>::new();
+        filter.add_value(1.0);
+        filter.add_value(f32::NAN);
+        filter.add_value(3.0);
+        assert_eq!(filter.mean(), 2.0);
+        filter.add_value(5.0);
+        filter.add_value(7.0);
+        assert_eq!(filter.mean(), 5.0);
+        filter.clear();
+        assert_eq!(filter.try_mean(), None);
+    }
+}
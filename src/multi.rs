@@ -0,0 +1,201 @@
+//! A moving median filter for several channels that are sampled together.
+
+use crate::{insert_sorted, middle, remove_sorted, Sample};
+
+/// A no-std moving median filter for C channels sampled together, such as the axes of an
+/// accelerometer, with a window of the last N samples.
+///
+/// All channels share one index and count, so they stay aligned in time and are filled and
+/// evicted together. Every channel keeps its own sorted buffer, like the one of
+/// [`MovingMedian`](crate::MovingMedian), and its median is calculated independently of the others.
+/// NaN values are kept in the window but left out of the median of their channel, like
+/// [`NanPolicy::Ignore`](crate::NanPolicy::Ignore).
+///
+/// ```
+/// use moving_median::MultiMovingMedian;
+///
+/// let mut filter = MultiMovingMedian::<f32, 3, 2>::new();
+/// filter.add_sample([1.0, -1.0]);
+/// filter.add_sample([50.0, -2.0]);
+/// filter.add_sample([2.0, -90.0]);
+///
+/// assert_eq!(filter.median(), [2.0, -2.0]);
+/// ```
+#[derive(Clone)]
+pub struct MultiMovingMedian<T, const N: usize, const C: usize> {
+    // One row of C channel values per sample, so a sample is evicted from all channels at once
+    buffer: [[T; C]; N],
+    // Row the next sample is written to, shared by all channels
+    index: usize,
+    // Number of rows in use
+    count: usize,
+    // The values of each channel other than NaN in ascending order, per channel since NaN
+    // values leave the channels with different numbers of values
+    sorted: [[T; N]; C],
+    sorted_len: [usize; C],
+}
+
+impl<T: Sample, const N: usize, const C: usize> MultiMovingMedian<T, N, C> {
+    /// Create a new multi-channel moving median filter with a fixed-size buffer of N samples.
+    pub fn new() -> Self {
+        Self {
+            buffer: [[T::zero(); C]; N],
+            index: 0,
+            count: 0,
+            sorted: [[T::zero(); N]; C],
+            sorted_len: [0; C],
+        }
+    }
+
+    /// Add a new sample, one value per channel, to the buffer.
+    /// If the buffer is full, the oldest sample will be replaced.
+    /// Returns the replaced oldest sample, or `None` while the buffer is still filling up.
+    pub fn add_sample(&mut self, sample: [T; C]) -> Option<[T; C]> {
+        let evicted = if self.count == N {
+            let oldest = self.buffer[self.index];
+            for (channel, &value) in oldest.iter().enumerate() {
+                self.remove_sorted(channel, value);
+            }
+            Some(oldest)
+        } else {
+            self.count += 1;
+            None
+        };
+        for (channel, &value) in sample.iter().enumerate() {
+            self.insert_sorted(channel, value);
+        }
+        self.buffer[self.index] = sample;
+        // Move to the next index, wrapping around if necessary
+        self.index = (self.index + 1) % N;
+        evicted
+    }
+
+    /// Remove all samples from the buffer, so the filter starts over as if it was new.
+    pub fn clear(&mut self) {
+        self.index = 0;
+        self.count = 0;
+        self.sorted_len = [0; C];
+    }
+
+    /// Number of samples in the buffer, at most N. Samples with NaN values are included.
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    /// Returns `true` if no samples have been added.
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Returns `true` once N samples have been added and the buffer has warmed up.
+    pub fn is_full(&self) -> bool {
+        self.count == N
+    }
+
+    /// Size N of the buffer, which is the number of samples the filter holds once it is full.
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
+    /// Calculate the median of every channel over the samples in the buffer.
+    /// If the number of values is even, the median is the average of the two middle values,
+    /// rounded towards negative infinity for integers (see [`Sample::midpoint`]).
+    /// Channels without values other than NaN have a median of zero.
+    pub fn median(&self) -> [T; C] {
+        self.try_median().map(|median| median.unwrap_or(T::zero()))
+    }
+
+    /// Calculate the median of every channel, like [`median`](Self::median).
+    /// The median of a channel is `None` if it holds no values other than NaN.
+    pub fn try_median(&self) -> [Option<T>; C] {
+        core::array::from_fn(|channel| {
+            middle(
+                &self.sorted[channel][..self.sorted_len[channel]],
+                T::midpoint,
+            )
+        })
+    }
+
+    // Insert a value into the sorted buffer of its channel, unless it is NaN
+    fn insert_sorted(&mut self, channel: usize, value: T) {
+        self.sorted_len[channel] =
+            insert_sorted(&mut self.sorted[channel], self.sorted_len[channel], value);
+    }
+
+    // Remove a value that is in the buffer from the sorted buffer of its channel
+    fn remove_sorted(&mut self, channel: usize, value: T) {
+        self.sorted_len[channel] =
+            remove_sorted(&mut self.sorted[channel], self.sorted_len[channel], value);
+    }
+}
+
+impl<T: Sample, const N: usize, const C: usize> Default for MultiMovingMedian<T, N, C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::XorShift;
+    use crate::MovingMedian;
+
+    #[test]
+    fn channels_reject_their_own_outliers() {
+        let mut filter = MultiMovingMedian::<i32, 5, 3>::new();
+        let samples = [
+            [10, 0, -5],
+            [900, 1, -5],
+            [11, 1, -5],
+            [12, -700, -6],
+            [10, 2, 400],
+        ];
+        for (i, sample) in samples.into_iter().enumerate() {
+            assert_eq!(filter.add_sample(sample), None);
+            assert_eq!(filter.len(), i + 1);
+        }
+        assert!(filter.is_full());
+        assert_eq!(filter.median(), [11, 1, -5]);
+        // The oldest sample is evicted from all channels at once
+        assert_eq!(filter.add_sample([0, 0, 0]), Some(samples[0]));
+        assert_eq!(filter.median(), [11, 1, -5]);
+    }
+
+    #[test]
+    fn channels_match_separate_filters() {
+        let mut rng = XorShift(0x2545_f491_4f6c_dd1d);
+        let mut filter = MultiMovingMedian::<f64, 4, 3>::new();
+        let mut separate: [MovingMedian<f64, 4>; 3] = core::array::from_fn(|_| MovingMedian::new());
+        for _ in 0..2_000 {
+            let sample = core::array::from_fn(|_| match rng.next() % 16 {
+                0 => f64::NAN,
+                value => value as f64,
+            });
+            filter.add_sample(sample);
+            for (channel, value) in separate.iter_mut().zip(sample) {
+                channel.add_value(value);
+            }
+            assert_eq!(
+                filter.try_median(),
+                separate.each_ref().map(|channel| channel.try_median())
+            );
+        }
+    }
+
+    #[test]
+    fn warm_up_and_clear_match_single_channel() {
+        let mut filter = MultiMovingMedian::<u8, 3, 2>::new();
+        assert!(filter.is_empty());
+        assert_eq!(filter.try_median(), [None, None]);
+        assert_eq!(filter.median(), [0, 0]);
+        filter.add_sample([4, 8]);
+        filter.add_sample([2, 6]);
+        assert!(!filter.is_full());
+        assert_eq!(filter.median(), [3, 7]);
+        filter.clear();
+        assert!(filter.is_empty());
+        filter.add_sample([1, 2]);
+        assert_eq!(filter.median(), [1, 2]);
+    }
+}
@@ -0,0 +1,303 @@
+//! An adapter for scalar types that implement `num-traits`, behind the `num-traits` feature.
+
+use num_traits::{Bounded, FromPrimitive, Num, ToPrimitive};
+
+use crate::Sample;
+
+/// Wraps any scalar type that implements the `num-traits` traits, so it can be filtered without
+/// implementing [`Sample`] for it.
+///
+/// [`Sample`] is implemented for the wrapper using only [`Num`] arithmetic, the limits of
+/// [`Bounded`] and the conversions of [`FromPrimitive`] and [`ToPrimitive`], so scalar types of
+/// other crates, such as wrappers used in DSP code, plug in directly. The midpoint of two values is
+/// calculated from their halves, so it does not overflow, and like for the primitive integers it
+/// is rounded towards negative infinity for types whose division truncates. An absolute difference
+/// that does not fit into such a type saturates at its maximum. Sums for the mean are taken in
+/// `f64`, so they do not overflow, and the mean is rounded towards negative infinity for types
+/// whose division truncates. Values beyond 2^53 are rounded to the nearest `f64` on the way.
+///
+/// ```
+/// use moving_median::{MovingMedian, NumSample};
+///
+/// let mut filter = MovingMedian::<NumSample<i32>, 3>::new();
+/// filter.add_value(NumSample(1));
+/// filter.add_value(NumSample(8));
+///
+/// assert_eq!(filter.median(), NumSample(4));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Default)]
+pub struct NumSample<T>(pub T);
+
+impl<T: Num + Copy + PartialOrd> NumSample<T> {
+    // The ordered pair of two values, lower value first
+    fn ordered(a: T, b: T) -> (T, T) {
+        if a <= b {
+            (a, b)
+        } else {
+            (b, a)
+        }
+    }
+
+    fn two() -> T {
+        T::one() + T::one()
+    }
+
+    // Whether division truncates, as it does for integers, rather than being exact
+    fn truncates() -> bool {
+        T::one() / Self::two() == T::zero()
+    }
+}
+
+impl<T: Num + Bounded + FromPrimitive + ToPrimitive + Copy + PartialOrd> Sample for NumSample<T> {
+    type Accumulator = f64;
+
+    fn zero() -> Self {
+        Self(T::zero())
+    }
+
+    fn midpoint(self, other: Self) -> Self {
+        let two = Self::two();
+        let half = self.0 / two + other.0 / two;
+        if !Self::truncates() {
+            return Self(half);
+        }
+        // Add half of the remainders the halves dropped, rounded towards negative infinity
+        let remainders = self.0 % two + other.0 % two;
+        let mut rounding = remainders / two;
+        if rounding * two > remainders {
+            rounding = rounding - T::one();
+        }
+        Self(half + rounding)
+    }
+
+    fn lerp(self, other: Self, t: f64) -> Self {
+        // The difference of the values can overflow the wrapped type, their distance in f64 cannot
+        let distance = other.to_f64() - self.to_f64();
+        match T::from_f64(distance * t) {
+            Some(step) => Self(self.0 + step),
+            // A step too large for the type still lands between the two values
+            None => Self::from_f64(self.to_f64() + distance * t),
+        }
+    }
+
+    fn abs_diff(self, other: Self) -> Self {
+        let (low, high) = Self::ordered(self.0, other.0);
+        // The difference overflows if it is greater than the maximum, which only a negative lower
+        // value allows, and then `low + max` does not overflow
+        if Self::truncates() && low < T::zero() && high > low + T::max_value() {
+            return Self(T::max_value());
+        }
+        Self(high - low)
+    }
+
+    fn accumulate(self) -> f64 {
+        self.to_f64()
+    }
+
+    fn mean(sum: f64, count: usize) -> Self {
+        let mean = sum / count as f64;
+        let rounded = Self::from_f64(mean);
+        // The conversion rounds towards zero, which is one too high for a negative mean
+        if Self::truncates() && rounded.to_f64() > mean {
+            Self(rounded.0 - T::one())
+        } else {
+            rounded
+        }
+    }
+
+    fn to_f64(self) -> f64 {
+        self.0.to_f64().unwrap_or(f64::NAN)
+    }
+
+    fn from_f64(value: f64) -> Self {
+        Self(T::from_f64(value).unwrap_or(T::zero()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::ops::{Add, Div, Mul, Rem, Sub};
+
+    use num_traits::{One, Zero};
+
+    use super::*;
+    use crate::MovingMedian;
+
+    // A scalar type of another crate that only implements the num-traits traits
+    #[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+    struct Millivolts(i32);
+
+    macro_rules! impl_op {
+        ($($op:ident::$method:ident),*) => {
+            $(
+                impl $op for Millivolts {
+                    type Output = Self;
+
+                    fn $method(self, other: Self) -> Self {
+                        Self(self.0.$method(other.0))
+                    }
+                }
+            )*
+        };
+    }
+
+    impl_op!(Add::add, Sub::sub, Mul::mul, Div::div, Rem::rem);
+
+    impl Zero for Millivolts {
+        fn zero() -> Self {
+            Self(0)
+        }
+
+        fn is_zero(&self) -> bool {
+            self.0 == 0
+        }
+    }
+
+    impl One for Millivolts {
+        fn one() -> Self {
+            Self(1)
+        }
+    }
+
+    impl Bounded for Millivolts {
+        fn min_value() -> Self {
+            Self(i32::MIN)
+        }
+
+        fn max_value() -> Self {
+            Self(i32::MAX)
+        }
+    }
+
+    impl Num for Millivolts {
+        type FromStrRadixErr = core::num::ParseIntError;
+
+        fn from_str_radix(text: &str, radix: u32) -> Result<Self, Self::FromStrRadixErr> {
+            i32::from_str_radix(text, radix).map(Self)
+        }
+    }
+
+    impl FromPrimitive for Millivolts {
+        fn from_i64(value: i64) -> Option<Self> {
+            i32::try_from(value).ok().map(Self)
+        }
+
+        fn from_u64(value: u64) -> Option<Self> {
+            i32::try_from(value).ok().map(Self)
+        }
+    }
+
+    impl ToPrimitive for Millivolts {
+        fn to_i64(&self) -> Option<i64> {
+            Some(self.0.into())
+        }
+
+        fn to_u64(&self) -> Option<u64> {
+            self.0.try_into().ok()
+        }
+    }
+
+    fn mv(value: i32) -> NumSample<Millivolts> {
+        NumSample(Millivolts(value))
+    }
+
+    #[test]
+    fn user_defined_num_type_can_be_filtered() {
+        let mut filter = MovingMedian::<NumSample<Millivolts>, 5>::new();
+        assert_eq!(filter.median(), mv(0));
+        filter.add_values(&[mv(3300), mv(3290), mv(9999), mv(3310)]);
+        assert_eq!(filter.median(), mv(3305));
+        filter.add_value(mv(0));
+        assert_eq!(filter.median(), mv(3300));
+        assert_eq!(filter.range(), Some(mv(9999)));
+        assert_eq!(filter.mean(), mv(3979));
+        assert_eq!(
+            filter.quantile(0.5, crate::Interpolation::Linear),
+            Some(mv(3300))
+        );
+    }
+
+    #[test]
+    fn i16_median_with_num_traits_enabled() {
+        let mut filter = MovingMedian::<i16, 3>::new();
+        let mut wrapped = MovingMedian::<NumSample<i16>, 3>::new();
+        for (value, median) in [(-300, -300), (120, -90), (7, 7), (i16::MAX, 120)] {
+            filter.add_value(value);
+            wrapped.add_value(NumSample(value));
+            assert_eq!(filter.median(), median);
+            assert_eq!(wrapped.median(), NumSample(median));
+        }
+    }
+
+    #[test]
+    fn mean_of_large_values_does_not_overflow() {
+        let mut filter = MovingMedian::<NumSample<u8>, 2>::new();
+        filter.add_values(&[NumSample(200), NumSample(200)]);
+        assert_eq!(filter.mean(), NumSample(200));
+        filter.add_value(NumSample(u8::MAX));
+        assert_eq!(filter.mean(), NumSample(227));
+
+        // The count does not fit into `i8`, which must not leave the sum undivided
+        let mut filter = MovingMedian::<NumSample<i8>, 200>::new();
+        for _ in 0..200 {
+            filter.add_value(NumSample(i8::MIN));
+        }
+        assert_eq!(filter.mean(), NumSample(i8::MIN));
+        filter.add_value(NumSample(i8::MIN + 1));
+        assert_eq!(filter.mean(), NumSample(i8::MIN));
+
+        let mut filter = MovingMedian::<NumSample<Millivolts>, 2>::new();
+        filter.add_values(&[mv(i32::MAX), mv(i32::MAX - 1)]);
+        assert_eq!(filter.mean(), mv(i32::MAX - 1));
+        filter.add_values(&[mv(-3), mv(-4)]);
+        assert_eq!(filter.mean(), mv(-4));
+    }
+
+    #[test]
+    fn midpoint_does_not_overflow() {
+        let high = NumSample(i32::MAX);
+        assert_eq!(
+            high.midpoint(NumSample(i32::MAX - 2)),
+            NumSample(i32::MAX - 1)
+        );
+        assert_eq!(NumSample(u8::MAX).midpoint(NumSample(1)), NumSample(128));
+        assert_eq!(NumSample(2.0).midpoint(NumSample(-1.0)), NumSample(0.5));
+    }
+
+    #[test]
+    fn signed_extremes_do_not_overflow() {
+        let (min, max) = (NumSample(i32::MIN), NumSample(i32::MAX));
+        assert_eq!(min.midpoint(max), NumSample(-1));
+        assert_eq!(max.midpoint(min), NumSample(-1));
+        assert_eq!(min.midpoint(min), min);
+        assert_eq!(max.midpoint(max), max);
+        assert_eq!(NumSample(-3).midpoint(NumSample(-4)), NumSample(-4));
+        assert_eq!(NumSample(-3).midpoint(NumSample(0)), NumSample(-2));
+        assert_eq!(NumSample(3).midpoint(NumSample(-4)), NumSample(-1));
+        assert_eq!(NumSample(-7).midpoint(NumSample(-7)), NumSample(-7));
+
+        assert_eq!(min.abs_diff(max), max);
+        assert_eq!(max.abs_diff(NumSample(-1)), max);
+        assert_eq!(NumSample(-1).abs_diff(max), max);
+        assert_eq!(NumSample(i32::MIN + 1).abs_diff(NumSample(0)), max);
+        assert_eq!(NumSample(-5).abs_diff(NumSample(7)), NumSample(12));
+        assert_eq!(
+            NumSample(f64::MAX).abs_diff(NumSample(-f64::MAX)),
+            NumSample(f64::INFINITY)
+        );
+
+        assert_eq!(min.lerp(max, 0.5), NumSample(-1));
+        assert_eq!(min.lerp(max, 1.0), max);
+
+        // The filter agrees with the primitive implementation at the limits
+        let values = [i32::MIN, i32::MAX, i32::MIN + 1, i32::MAX - 1];
+        let mut wrapped = MovingMedian::<NumSample<i32>, 4>::new();
+        let mut primitive = MovingMedian::<i32, 4>::new();
+        for value in values {
+            wrapped.add_value(NumSample(value));
+            primitive.add_value(value);
+            assert_eq!(wrapped.median(), NumSample(primitive.median()));
+            assert_eq!(wrapped.range(), primitive.range().map(NumSample));
+        }
+    }
+}
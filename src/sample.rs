@@ -0,0 +1,337 @@
+//! Numeric operations the filters need from their sample type.
+
+use core::num::Wrapping;
+use core::ops::{Add, Sub};
+
+/// A sample type that can be stored in a [`MovingMedian`](crate::MovingMedian).
+///
+/// Implemented for all primitive integer and floating point types. Other numeric types, such as
+/// fixed-point wrappers, can implement it too, often by delegating to the primitive they wrap.
+///
+/// ```
+/// use moving_median::{MovingMedian, Sample};
+///
+/// // A fixed-point number with 8 fractional bits
+/// #[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+/// struct Q8(i32);
+///
+/// impl Sample for Q8 {
+///     type Accumulator = <i32 as Sample>::Accumulator;
+///
+///     fn zero() -> Self {
+///         Q8(0)
+///     }
+///
+///     fn midpoint(self, other: Self) -> Self {
+///         Q8(Sample::midpoint(self.0, other.0))
+///     }
+///
+///     fn lerp(self, other: Self, t: f64) -> Self {
+///         Q8(Sample::lerp(self.0, other.0, t))
+///     }
+///
+///     fn abs_diff(self, other: Self) -> Self {
+///         Q8(Sample::abs_diff(self.0, other.0))
+///     }
+///
+///     fn accumulate(self) -> Self::Accumulator {
+///         self.0.accumulate()
+///     }
+///
+///     fn mean(sum: Self::Accumulator, count: usize) -> Self {
+///         Q8(i32::mean(sum, count))
+///     }
+///
+///     fn to_f64(self) -> f64 {
+///         self.0 as f64 / 256.0
+///     }
+///
+///     fn from_f64(value: f64) -> Self {
+///         Q8(i32::from_f64(value * 256.0))
+///     }
+/// }
+///
+/// let mut filter = MovingMedian::<Q8, 3>::new();
+/// filter.add_value(Q8(384)); // 1.5
+/// filter.add_value(Q8(128)); // 0.5
+///
+/// assert_eq!(filter.median(), Q8(256));
+/// assert_eq!(filter.median().to_f64(), 1.0);
+/// ```
+pub trait Sample: Copy + PartialOrd {
+    /// A type wide enough to add up a window of samples without overflowing.
+    ///
+    /// Integers use 128-bit sums that wrap around, so adding to and taking from a running sum never
+    /// panics, and the sum is exact whenever the sum of the window fits into 128 bits. Only windows
+    /// of 128-bit integers near their limits do not.
+    type Accumulator: Copy
+        + PartialOrd
+        + Add<Output = Self::Accumulator>
+        + Sub<Output = Self::Accumulator>;
+
+    /// The zero value, used to initialize the buffer and as the median of an empty filter.
+    fn zero() -> Self;
+
+    /// The value halfway between `self` and `other`, used to average the two middle values.
+    ///
+    /// For integers the result is rounded towards negative infinity and never overflows, and for
+    /// floats it stays finite for any two finite values.
+    fn midpoint(self, other: Self) -> Self;
+
+    /// The value halfway between `self` and `other`, rounded up where the result is not exact.
+    ///
+    /// For integers the result is rounded towards positive infinity and never overflows.
+    /// The default implementation uses [`midpoint`](Self::midpoint), which suits types whose average is exact.
+    fn midpoint_ceil(self, other: Self) -> Self {
+        self.midpoint(other)
+    }
+
+    /// Returns `true` if the value cannot be ordered, such as a floating point NaN.
+    ///
+    /// The default implementation checks whether the value is unordered with respect to itself.
+    fn is_nan(self) -> bool {
+        self.partial_cmp(&self).is_none()
+    }
+
+    /// The value a fraction `t` of the way from `self` to `other`, where `t` is between 0 and 1
+    /// and `self` is not greater than `other`. Used for interpolating between neighbouring values.
+    ///
+    /// For integers the result is rounded towards negative infinity.
+    fn lerp(self, other: Self, t: f64) -> Self;
+
+    /// The absolute difference between `self` and `other`.
+    ///
+    /// For signed integers a difference that does not fit into the type saturates at its maximum.
+    fn abs_diff(self, other: Self) -> Self;
+
+    /// Convert the sample into the accumulator type, so it can be added to a sum.
+    fn accumulate(self) -> Self::Accumulator;
+
+    /// Divide a sum of `count` samples by `count`, where `count` is at least one.
+    ///
+    /// For integers the result is rounded towards negative infinity.
+    fn mean(sum: Self::Accumulator, count: usize) -> Self;
+
+    /// Convert the sample into double precision, for statistics such as the variance.
+    fn to_f64(self) -> f64;
+
+    /// Convert a double precision result back into the sample type.
+    ///
+    /// For integers the result is rounded towards negative infinity and saturates at the limits of the type.
+    fn from_f64(value: f64) -> Self;
+}
+
+macro_rules! impl_sample_for_integer {
+    ($($t:ty => $acc:ty, $unsigned:ty);*) => {
+        $(
+            impl Sample for $t {
+                type Accumulator = Wrapping<$acc>;
+
+                fn zero() -> Self {
+                    0
+                }
+
+                fn midpoint(self, other: Self) -> Self {
+                    // The shared bits plus half of the differing bits, which cannot overflow
+                    (self & other) + ((self ^ other) >> 1)
+                }
+
+                fn midpoint_ceil(self, other: Self) -> Self {
+                    // All set bits minus half of the differing bits, which cannot overflow
+                    (self | other) - ((self ^ other) >> 1)
+                }
+
+                fn is_nan(self) -> bool {
+                    false
+                }
+
+                fn lerp(self, other: Self, t: f64) -> Self {
+                    // The distance always fits into the unsigned type of the same width, and so does
+                    // the step, which must not be rounded past the distance
+                    let distance = <$t>::abs_diff(other, self);
+                    let step = ((distance as f64 * t) as $unsigned).min(distance);
+                    // The result lies between the two values, so the wrapping addition is exact
+                    self.wrapping_add(step as $t)
+                }
+
+                fn abs_diff(self, other: Self) -> Self {
+                    Self::try_from(<$t>::abs_diff(self, other)).unwrap_or(Self::MAX)
+                }
+
+                fn accumulate(self) -> Wrapping<$acc> {
+                    Wrapping(self as $acc)
+                }
+
+                fn mean(sum: Wrapping<$acc>, count: usize) -> Self {
+                    // The mean of the samples always fits back into the sample type
+                    sum.0.div_euclid(count as $acc) as $t
+                }
+
+                fn to_f64(self) -> f64 {
+                    self as f64
+                }
+
+                fn from_f64(value: f64) -> Self {
+                    // The cast rounds towards zero and saturates, so step down for negative fractions
+                    let truncated = value as $t;
+                    if (truncated as f64) > value {
+                        truncated.saturating_sub(1)
+                    } else {
+                        truncated
+                    }
+                }
+            }
+        )*
+    };
+}
+
+macro_rules! impl_sample_for_float {
+    ($($t:ty),*) => {
+        $(
+            impl Sample for $t {
+                // Sums are kept in double precision to limit rounding errors
+                type Accumulator = f64;
+
+                fn zero() -> Self {
+                    0.0
+                }
+
+                fn midpoint(self, other: Self) -> Self {
+                    let sum = self + other;
+                    if sum.is_finite() {
+                        sum / 2.0
+                    } else {
+                        // Halving large values first is exact and keeps the sum in range
+                        self / 2.0 + other / 2.0
+                    }
+                }
+
+                fn is_nan(self) -> bool {
+                    <$t>::is_nan(self)
+                }
+
+                fn lerp(self, other: Self, t: f64) -> Self {
+                    // Weighting both ends keeps the result exact at either end and halfway
+                    (self as f64 * (1.0 - t) + other as f64 * t) as $t
+                }
+
+                fn abs_diff(self, other: Self) -> Self {
+                    if self > other {
+                        self - other
+                    } else {
+                        other - self
+                    }
+                }
+
+                fn accumulate(self) -> f64 {
+                    self as f64
+                }
+
+                fn mean(sum: f64, count: usize) -> Self {
+                    (sum / count as f64) as $t
+                }
+
+                fn to_f64(self) -> f64 {
+                    self as f64
+                }
+
+                fn from_f64(value: f64) -> Self {
+                    value as $t
+                }
+            }
+        )*
+    };
+}
+
+impl_sample_for_integer!(
+    i8 => i128, u8; i16 => i128, u16; i32 => i128, u32; i64 => i128, u64; i128 => i128, u128;
+    isize => i128, usize; u8 => u128, u8; u16 => u128, u16; u32 => u128, u32; u64 => u128, u64;
+    u128 => u128, u128; usize => u128, usize
+);
+impl_sample_for_float!(f32, f64);
+
+/// The running sum a [`MovingMedian`](crate::MovingMedian) keeps of the ordered values in its
+/// window, so the [`mean`](crate::MovingMedian::mean) does not add up the window on every call.
+///
+/// Implemented for every [`Sample`], summing in its [`Accumulator`](Sample::Accumulator), and without
+/// a sum for `bool`, `char`, string slices and tuples of up to four values. Other types that are
+/// only ordered, for the order statistics such as [`median_low`](crate::MovingMedian::median_low),
+/// implement it without a sum too:
+///
+/// ```
+/// use moving_median::{MovingMedian, WindowSum};
+///
+/// #[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+/// struct Tick(u32);
+///
+/// impl WindowSum for Tick {
+///     type Sum = ();
+///
+///     fn to_sum(self) {}
+/// }
+///
+/// let mut filter = MovingMedian::<Tick, 3>::with_default(Tick(0));
+/// filter.add_values(&[Tick(4), Tick(9), Tick(1)]);
+/// assert_eq!(filter.median_low(), Some(Tick(4)));
+/// ```
+pub trait WindowSum: Copy + PartialOrd {
+    /// The type of the sum, `()` for types that are not summed.
+    type Sum: Copy;
+
+    /// The sum of `self` alone, which starts the running sum.
+    fn to_sum(self) -> Self::Sum;
+
+    /// `sum` with `self` added to it. The default implementation returns `sum` unchanged.
+    fn add_to_sum(self, sum: Self::Sum) -> Self::Sum {
+        sum
+    }
+
+    /// `sum` with `self`, which was added to it before, taken out again. The default
+    /// implementation returns `sum` unchanged.
+    fn remove_from_sum(self, sum: Self::Sum) -> Self::Sum {
+        sum
+    }
+}
+
+// Types of the standard library that are ordered but have no arithmetic keep no sum
+macro_rules! impl_window_sum_without_sum {
+    ($(<$($param:ident),*> $t:ty),*) => {
+        $(
+            impl<$($param: Copy + PartialOrd),*> WindowSum for $t {
+                type Sum = ();
+
+                fn to_sum(self) {}
+            }
+        )*
+    };
+}
+
+impl_window_sum_without_sum!(
+    <> bool,
+    <> char,
+    <A, B> (A, B),
+    <A, B, C> (A, B, C),
+    <A, B, C, D> (A, B, C, D)
+);
+
+impl WindowSum for &str {
+    type Sum = ();
+
+    fn to_sum(self) {}
+}
+
+impl<T: Sample> WindowSum for T {
+    type Sum = T::Accumulator;
+
+    fn to_sum(self) -> T::Accumulator {
+        self.accumulate()
+    }
+
+    fn add_to_sum(self, sum: T::Accumulator) -> T::Accumulator {
+        sum + self.accumulate()
+    }
+
+    fn remove_from_sum(self, sum: T::Accumulator) -> T::Accumulator {
+        sum - self.accumulate()
+    }
+}
@@ -0,0 +1,405 @@
+//! `serde` support for [`MovingMedian`], behind the `serde` feature.
+
+use core::fmt;
+use core::marker::PhantomData;
+
+use serde::de::{self, Deserialize, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde::ser::{Serialize, SerializeStruct, SerializeTuple, Serializer};
+
+use crate::{InvalidState, MovingMedian, Sample, Snapshot};
+
+const FIELDS: &[&str] = &[
+    "buffer",
+    "index",
+    "count",
+    "nan_policy",
+    "even_strategy",
+    "effective_len",
+    "total",
+    "empty_median",
+];
+
+/// Serializes the buffer in ring order together with the index and count, so a deserialized
+/// filter continues exactly where the original left off. The sorted buffer is rebuilt on deserialization.
+/// The number of values added and the median of an empty filter set with
+/// [`clear_with_default`](MovingMedian::clear_with_default) are kept as well.
+impl<T: Sample + Serialize, const N: usize> Serialize for MovingMedian<T, N> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("MovingMedian", FIELDS.len())?;
+        state.serialize_field("buffer", &Buffer(&self.buffer))?;
+        state.serialize_field("index", &self.index)?;
+        state.serialize_field("count", &self.count)?;
+        state.serialize_field("nan_policy", &self.nan_policy)?;
+        state.serialize_field("even_strategy", &self.even_strategy)?;
+        state.serialize_field("effective_len", &self.effective_len)?;
+        state.serialize_field("total", &self.total)?;
+        state.serialize_field("empty_median", &self.empty_median)?;
+        state.end()
+    }
+}
+
+/// Rejects data where the effective length, index or count does not fit the buffer size N, instead of
+/// producing a filter that panics later.
+impl<'de, T: Sample + Deserialize<'de>, const N: usize> Deserialize<'de> for MovingMedian<T, N> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_struct("MovingMedian", FIELDS, FilterVisitor(PhantomData))
+    }
+}
+
+// Serializes a buffer as a tuple of exactly N values
+struct Buffer<'a, T, const N: usize>(&'a [T; N]);
+
+impl<T: Serialize, const N: usize> Serialize for Buffer<'_, T, N> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut tuple = serializer.serialize_tuple(N)?;
+        for value in self.0 {
+            tuple.serialize_element(value)?;
+        }
+        tuple.end()
+    }
+}
+
+// Deserializes a tuple of exactly N values into a buffer
+struct BufferSeed<T, const N: usize>([T; N]);
+
+impl<'de, T: Sample + Deserialize<'de>, const N: usize> Deserialize<'de> for BufferSeed<T, N> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_tuple(N, BufferVisitor(PhantomData))
+    }
+}
+
+struct BufferVisitor<T, const N: usize>(PhantomData<T>);
+
+impl<'de, T: Sample + Deserialize<'de>, const N: usize> Visitor<'de> for BufferVisitor<T, N> {
+    type Value = BufferSeed<T, N>;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "a buffer of {} values", N)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut buffer = [T::zero(); N];
+        for (i, slot) in buffer.iter_mut().enumerate() {
+            *slot = seq
+                .next_element()?
+                .ok_or_else(|| de::Error::invalid_length(i, &self))?;
+        }
+        if seq.next_element::<de::IgnoredAny>()?.is_some() {
+            return Err(de::Error::invalid_length(N + 1, &self));
+        }
+        Ok(BufferSeed(buffer))
+    }
+}
+
+struct FilterVisitor<T, const N: usize>(PhantomData<T>);
+
+impl<T: Sample, const N: usize> FilterVisitor<T, N> {
+    // Check the invariants and rebuild the sorted buffer from the window, like a snapshot
+    #[allow(clippy::too_many_arguments)]
+    fn build<E: de::Error>(
+        buffer: [T; N],
+        index: usize,
+        count: usize,
+        nan_policy: crate::NanPolicy,
+        even_strategy: crate::EvenStrategy,
+        effective_len: usize,
+        total: u64,
+        empty_median: Option<T>,
+    ) -> Result<MovingMedian<T, N>, E> {
+        let snapshot = Snapshot {
+            buffer,
+            count,
+            index,
+            effective_len,
+            nan_policy,
+            even_strategy,
+            total,
+            empty_median,
+        };
+        snapshot.into_filter().map_err(|error| match error {
+            InvalidState::Window(len) => E::invalid_value(
+                de::Unexpected::Unsigned(len as u64),
+                &"an effective length from 1 to the buffer size",
+            ),
+            InvalidState::Index(index) => E::invalid_value(
+                de::Unexpected::Unsigned(index as u64),
+                &"an index less than the effective length",
+            ),
+            InvalidState::Count(count) => E::invalid_value(
+                de::Unexpected::Unsigned(count as u64),
+                &"a count not greater than the effective length",
+            ),
+        })
+    }
+}
+
+impl<'de, T: Sample + Deserialize<'de>, const N: usize> Visitor<'de> for FilterVisitor<T, N> {
+    type Value = MovingMedian<T, N>;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a moving median filter")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let missing = |i| de::Error::invalid_length(i, &"a moving median filter");
+        let BufferSeed(buffer) = seq.next_element()?.ok_or_else(|| missing(0))?;
+        let index = seq.next_element()?.ok_or_else(|| missing(1))?;
+        let count = seq.next_element()?.ok_or_else(|| missing(2))?;
+        let nan_policy = seq.next_element()?.ok_or_else(|| missing(3))?;
+        let even_strategy = seq.next_element()?.ok_or_else(|| missing(4))?;
+        let effective_len = seq.next_element()?.ok_or_else(|| missing(5))?;
+        let total = seq.next_element()?.ok_or_else(|| missing(6))?;
+        let empty_median = seq.next_element()?.ok_or_else(|| missing(7))?;
+        Self::build(
+            buffer,
+            index,
+            count,
+            nan_policy,
+            even_strategy,
+            effective_len,
+            total,
+            empty_median,
+        )
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut buffer = None;
+        let mut index = None;
+        let mut count = None;
+        let mut nan_policy = None;
+        let mut even_strategy = None;
+        let mut effective_len = None;
+        let mut total = None;
+        let mut empty_median = None;
+        while let Some(key) = map.next_key::<Field>()? {
+            match key {
+                Field::Buffer => buffer = Some(map.next_value::<BufferSeed<T, N>>()?.0),
+                Field::Index => index = Some(map.next_value()?),
+                Field::Count => count = Some(map.next_value()?),
+                Field::NanPolicy => nan_policy = Some(map.next_value()?),
+                Field::EvenStrategy => even_strategy = Some(map.next_value()?),
+                Field::EffectiveLen => effective_len = Some(map.next_value()?),
+                Field::Total => total = Some(map.next_value()?),
+                Field::EmptyMedian => empty_median = Some(map.next_value()?),
+            }
+        }
+        Self::build(
+            buffer.ok_or_else(|| de::Error::missing_field("buffer"))?,
+            index.ok_or_else(|| de::Error::missing_field("index"))?,
+            count.ok_or_else(|| de::Error::missing_field("count"))?,
+            nan_policy.unwrap_or_default(),
+            even_strategy.unwrap_or_default(),
+            effective_len.unwrap_or(N),
+            total.ok_or_else(|| de::Error::missing_field("total"))?,
+            empty_median.ok_or_else(|| de::Error::missing_field("empty_median"))?,
+        )
+    }
+}
+
+enum Field {
+    Buffer,
+    Index,
+    Count,
+    NanPolicy,
+    EvenStrategy,
+    EffectiveLen,
+    Total,
+    EmptyMedian,
+}
+
+impl<'de> Deserialize<'de> for Field {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct FieldVisitor;
+
+        impl Visitor<'_> for FieldVisitor {
+            type Value = Field;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a field of a moving median filter")
+            }
+
+            fn visit_str<E: de::Error>(self, value: &str) -> Result<Field, E> {
+                match value {
+                    "buffer" => Ok(Field::Buffer),
+                    "index" => Ok(Field::Index),
+                    "count" => Ok(Field::Count),
+                    "nan_policy" => Ok(Field::NanPolicy),
+                    "even_strategy" => Ok(Field::EvenStrategy),
+                    "effective_len" => Ok(Field::EffectiveLen),
+                    "total" => Ok(Field::Total),
+                    "empty_median" => Ok(Field::EmptyMedian),
+                    _ => Err(de::Error::unknown_field(value, FIELDS)),
+                }
+            }
+        }
+
+        deserializer.deserialize_identifier(FieldVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{EvenStrategy, NanPolicy};
+    use serde::Serialize;
+
+    // Mirrors the serialized layout of a filter, so tests can produce corrupt data
+    #[derive(Serialize)]
+    struct RawFilter {
+        buffer: [f32; 3],
+        index: usize,
+        count: usize,
+        nan_policy: NanPolicy,
+        even_strategy: EvenStrategy,
+        effective_len: usize,
+        total: u64,
+        empty_median: Option<f32>,
+    }
+
+    #[test]
+    fn round_trip_preserves_ring_order() {
+        let mut filter = MovingMedian::<f32, 3>::new();
+        filter.add_values(&[1.0, 5.0, 3.0, 4.0]);
+        let bytes = postcard::to_stdvec(&filter).unwrap();
+        let mut restored: MovingMedian<f32, 3> = postcard::from_bytes(&bytes).unwrap();
+        assert_eq!(restored.median(), filter.median());
+        assert_eq!(restored.len(), 3);
+        // The oldest value is still evicted first
+        assert_eq!(restored.add_value(0.0), Some(5.0));
+        assert_eq!(filter.add_value(0.0), Some(5.0));
+        assert_eq!(restored.median(), filter.median());
+    }
+
+    #[test]
+    fn round_trip_of_partially_filled_filter() {
+        let mut filter = MovingMedian::<i32, 4>::new();
+        filter.set_nan_policy(NanPolicy::Propagate);
+        filter.set_even_strategy(EvenStrategy::TakeUpper);
+        filter.add_values(&[7, -2]);
+        let bytes = postcard::to_stdvec(&filter).unwrap();
+        let restored: MovingMedian<i32, 4> = postcard::from_bytes(&bytes).unwrap();
+        assert_eq!(restored.try_median(), Some(7));
+        assert_eq!(restored.len(), 2);
+        assert_eq!(restored.nan_policy(), NanPolicy::Propagate);
+        assert_eq!(restored.even_strategy(), EvenStrategy::TakeUpper);
+    }
+
+    #[test]
+    fn count_greater_than_n_is_rejected() {
+        let raw = RawFilter {
+            buffer: [1.0, 2.0, 3.0],
+            index: 0,
+            count: 4,
+            nan_policy: NanPolicy::Ignore,
+            even_strategy: EvenStrategy::AverageMiddle,
+            effective_len: 3,
+            total: 3,
+            empty_median: None,
+        };
+        let bytes = postcard::to_stdvec(&raw).unwrap();
+        assert!(postcard::from_bytes::<MovingMedian<f32, 3>>(&bytes).is_err());
+    }
+
+    #[test]
+    fn index_out_of_buffer_is_rejected() {
+        let raw = RawFilter {
+            buffer: [1.0, 2.0, 3.0],
+            index: 3,
+            count: 3,
+            nan_policy: NanPolicy::Ignore,
+            even_strategy: EvenStrategy::AverageMiddle,
+            effective_len: 3,
+            total: 3,
+            empty_median: None,
+        };
+        let bytes = postcard::to_stdvec(&raw).unwrap();
+        assert!(postcard::from_bytes::<MovingMedian<f32, 3>>(&bytes).is_err());
+    }
+
+    #[test]
+    fn buffer_of_wrong_size_is_rejected() {
+        let mut filter = MovingMedian::<f32, 3>::new();
+        filter.add_values(&[1.0, 2.0]);
+        let bytes = postcard::to_stdvec(&filter).unwrap();
+        assert!(postcard::from_bytes::<MovingMedian<f32, 4>>(&bytes).is_err());
+    }
+
+    #[test]
+    fn round_trip_through_json() {
+        let mut filter = MovingMedian::<f64, 5>::new();
+        filter.add_values(&[3.5, -1.25, 8.0, 2.0, 4.5, 6.0, 7.5]);
+        let json = serde_json::to_string(&filter).unwrap();
+        let restored: MovingMedian<f64, 5> = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.median(), filter.median());
+        assert!(restored == filter);
+    }
+
+    #[test]
+    fn json_with_count_greater_than_n_is_rejected() {
+        let json = r#"{"buffer":[1.0,2.0,3.0],"index":0,"count":4,"nan_policy":"Ignore","total":4,"empty_median":null}"#;
+        assert!(serde_json::from_str::<MovingMedian<f64, 3>>(json).is_err());
+    }
+
+    #[test]
+    fn round_trip_preserves_shortened_window() {
+        let mut filter = MovingMedian::<i32, 6>::new();
+        filter.add_values(&[1, 2, 3, 4, 5]);
+        filter.set_window(3);
+        let bytes = postcard::to_stdvec(&filter).unwrap();
+        let mut restored: MovingMedian<i32, 6> = postcard::from_bytes(&bytes).unwrap();
+        assert_eq!(restored.effective_len(), 3);
+        assert!(restored == filter);
+        assert_eq!(restored.add_value(6), Some(3));
+    }
+
+    #[test]
+    fn index_beyond_effective_len_is_rejected() {
+        let raw = RawFilter {
+            buffer: [1.0, 2.0, 3.0],
+            index: 2,
+            count: 2,
+            nan_policy: NanPolicy::Ignore,
+            even_strategy: EvenStrategy::AverageMiddle,
+            effective_len: 2,
+            total: 2,
+            empty_median: None,
+        };
+        let bytes = postcard::to_stdvec(&raw).unwrap();
+        assert!(postcard::from_bytes::<MovingMedian<f32, 3>>(&bytes).is_err());
+    }
+
+    #[test]
+    fn round_trip_preserves_total_and_empty_median() {
+        let mut filter = MovingMedian::<i32, 3>::new();
+        filter.add_values(&[1, 2, 3, 4]);
+        filter.clear_with_default(-40);
+        filter.add_values(&[5, 6]);
+        assert_eq!(filter.total_pushed(), 2);
+
+        let bytes = postcard::to_stdvec(&filter).unwrap();
+        let mut restored: MovingMedian<i32, 3> = postcard::from_bytes(&bytes).unwrap();
+        assert_eq!(restored.total_pushed(), 2);
+        assert_eq!(restored.median(), 5);
+        // The filter is emptied without clearing, so the default stays in place
+        restored.pop_oldest();
+        restored.pop_oldest();
+        assert_eq!(restored.median(), -40);
+
+        let json = serde_json::to_string(&restored).unwrap();
+        let restored: MovingMedian<i32, 3> = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.total_pushed(), 2);
+        assert_eq!(restored.median(), -40);
+    }
+
+    #[test]
+    fn json_without_total_or_empty_median_is_rejected() {
+        let json = r#"{"buffer":[1.0,2.0,3.0],"index":2,"count":2,"empty_median":null}"#;
+        assert!(serde_json::from_str::<MovingMedian<f64, 3>>(json).is_err());
+        let json = r#"{"buffer":[1.0,2.0,3.0],"index":2,"count":2,"total":2}"#;
+        assert!(serde_json::from_str::<MovingMedian<f64, 3>>(json).is_err());
+        let json = r#"{"buffer":[1.0,2.0,3.0],"index":2,"count":2,"total":2,"empty_median":null}"#;
+        let filter = serde_json::from_str::<MovingMedian<f64, 3>>(json).unwrap();
+        assert_eq!(filter.total_pushed(), 2);
+        assert_eq!(filter.median(), 1.5);
+    }
+}
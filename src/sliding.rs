@@ -0,0 +1,211 @@
+//! A common interface for the sliding window filters of this crate.
+
+use crate::{LargeMovingMedian, MovingMax, MovingMean, MovingMedian, MovingMin, Sample};
+
+/// A filter over a sliding window of values, so code can be written for any of the filters in this crate.
+///
+/// The trait is object safe, so a filter can be chosen at runtime and used as a `&mut dyn SlidingFilter<T>`
+/// without an allocator.
+///
+/// ```
+/// use moving_median::{MovingMean, MovingMedian, SlidingFilter};
+///
+/// fn smooth(filter: &mut dyn SlidingFilter<f32>, values: &[f32]) -> Option<f32> {
+///     for &value in values {
+///         filter.push(value);
+///     }
+///     filter.output()
+/// }
+///
+/// let values = [1.0, 2.0, 9.0];
+/// assert_eq!(smooth(&mut MovingMedian::<f32, 3>::new(), &values), Some(2.0));
+/// assert_eq!(smooth(&mut MovingMean::<f32, 3>::new(), &values), Some(4.0));
+/// ```
+pub trait SlidingFilter<T> {
+    /// Add a new value to the window, replacing the oldest value once the window is full.
+    fn push(&mut self, value: T);
+
+    /// The output of the filter for the current window, or `None` if there is none yet.
+    fn output(&self) -> Option<T>;
+
+    /// Remove all values from the window, so the filter starts over as if it was new.
+    fn reset(&mut self);
+
+    /// Number of values in the window.
+    fn len(&self) -> usize;
+
+    /// Returns `true` if no values have been added.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// The output is the [`try_median`](MovingMedian::try_median).
+impl<T: Sample, const N: usize> SlidingFilter<T> for MovingMedian<T, N> {
+    fn push(&mut self, value: T) {
+        self.add_value(value);
+    }
+
+    fn output(&self) -> Option<T> {
+        self.try_median()
+    }
+
+    fn reset(&mut self) {
+        self.clear();
+    }
+
+    fn len(&self) -> usize {
+        self.len()
+    }
+}
+
+/// The output is the [`try_median`](LargeMovingMedian::try_median).
+impl<T: Sample, const N: usize> SlidingFilter<T> for LargeMovingMedian<T, N> {
+    fn push(&mut self, value: T) {
+        self.add_value(value);
+    }
+
+    fn output(&self) -> Option<T> {
+        self.try_median()
+    }
+
+    fn reset(&mut self) {
+        self.clear();
+    }
+
+    fn len(&self) -> usize {
+        self.len()
+    }
+}
+
+/// The output is the [`try_mean`](MovingMean::try_mean).
+impl<T: Sample, const N: usize> SlidingFilter<T> for MovingMean<T, N> {
+    fn push(&mut self, value: T) {
+        self.add_value(value);
+    }
+
+    fn output(&self) -> Option<T> {
+        self.try_mean()
+    }
+
+    fn reset(&mut self) {
+        self.clear();
+    }
+
+    fn len(&self) -> usize {
+        self.len()
+    }
+}
+
+/// The output is the [`min`](MovingMin::min).
+impl<T: Sample, const N: usize> SlidingFilter<T> for MovingMin<T, N> {
+    fn push(&mut self, value: T) {
+        self.add_value(value);
+    }
+
+    fn output(&self) -> Option<T> {
+        self.min()
+    }
+
+    fn reset(&mut self) {
+        self.clear();
+    }
+
+    fn len(&self) -> usize {
+        self.len()
+    }
+}
+
+/// The output is the [`max`](MovingMax::max).
+impl<T: Sample, const N: usize> SlidingFilter<T> for MovingMax<T, N> {
+    fn push(&mut self, value: T) {
+        self.add_value(value);
+    }
+
+    fn output(&self) -> Option<T> {
+        self.max()
+    }
+
+    fn reset(&mut self) {
+        self.clear();
+    }
+
+    fn len(&self) -> usize {
+        self.len()
+    }
+}
+
+/// The output is the [`try_median`](crate::MovingMedianDyn::try_median).
+#[cfg(feature = "alloc")]
+impl<T: Sample> SlidingFilter<T> for crate::MovingMedianDyn<T> {
+    fn push(&mut self, value: T) {
+        self.add_value(value);
+    }
+
+    fn output(&self) -> Option<T> {
+        self.try_median()
+    }
+
+    fn reset(&mut self) {
+        self.clear();
+    }
+
+    fn len(&self) -> usize {
+        self.len()
+    }
+}
+
+/// The output is the [`try_median`](crate::MovingMedianHeapless::try_median).
+#[cfg(feature = "heapless")]
+impl<T: Sample, const N: usize> SlidingFilter<T> for crate::MovingMedianHeapless<T, N> {
+    fn push(&mut self, value: T) {
+        self.add_value(value);
+    }
+
+    fn output(&self) -> Option<T> {
+        self.try_median()
+    }
+
+    fn reset(&mut self) {
+        self.clear();
+    }
+
+    fn len(&self) -> usize {
+        self.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The behaviour every filter shares, for a filter holding up to three values
+    fn check_contract(filter: &mut dyn SlidingFilter<f32>) {
+        assert!(filter.is_empty());
+        assert_eq!(filter.output(), None);
+        for (i, value) in [4.0, 4.0, 4.0, 4.0].into_iter().enumerate() {
+            filter.push(value);
+            assert_eq!(filter.len(), (i + 1).min(3));
+            // Every filter outputs the value of a window of equal values
+            assert_eq!(filter.output(), Some(4.0));
+        }
+        filter.reset();
+        assert!(filter.is_empty());
+        assert_eq!(filter.output(), None);
+        filter.push(1.0);
+        assert_eq!(filter.output(), Some(1.0));
+    }
+
+    #[test]
+    fn filters_share_the_contract() {
+        check_contract(&mut MovingMedian::<f32, 3>::new());
+        check_contract(&mut LargeMovingMedian::<f32, 3>::new());
+        check_contract(&mut MovingMean::<f32, 3>::new());
+        check_contract(&mut MovingMin::<f32, 3>::new());
+        check_contract(&mut MovingMax::<f32, 3>::new());
+        #[cfg(feature = "alloc")]
+        check_contract(&mut crate::MovingMedianDyn::<f32>::with_capacity(3));
+        #[cfg(feature = "heapless")]
+        check_contract(&mut crate::MovingMedianHeapless::<f32, 3>::new());
+    }
+}
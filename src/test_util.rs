@@ -0,0 +1,13 @@
+//! Helpers shared by the unit tests.
+
+/// Small xorshift generator so the randomized tests are reproducible without extra dependencies.
+pub(crate) struct XorShift(pub(crate) u64);
+
+impl XorShift {
+    pub(crate) fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+}
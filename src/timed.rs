@@ -0,0 +1,167 @@
+//! A moving median filter over the values that are recent enough, for irregularly arriving samples.
+
+use crate::{middle, Sample};
+
+/// A no-std moving median filter over the last N values that are no older than a maximum age.
+///
+/// Every value is added together with a timestamp in ticks of any clock, such as milliseconds
+/// since startup. The buffer holds the last N values like [`MovingMedian`](crate::MovingMedian)
+/// does, and [`median_at`](Self::median_at) leaves out the values that are older than `max_age` at
+/// the time of the query, so a burst of samples and a long pause are treated alike.
+/// Stale values are only skipped when the median is calculated, so the same filter can be queried
+/// with different maximum ages. Values with a timestamp after `now` count as fresh.
+/// NaN values are left out of the median.
+///
+/// ```
+/// use moving_median::TimedMovingMedian;
+///
+/// let mut filter = TimedMovingMedian::<f32, 8>::new();
+/// filter.add_value(10.0, 1_000);
+/// filter.add_value(20.0, 4_950);
+/// filter.add_value(22.0, 4_990);
+///
+/// // Only the last two values are at most 100 ticks old at 5000
+/// assert_eq!(filter.median_at(5_000, 100), Some(21.0));
+/// assert_eq!(filter.median_at(9_000, 100), None);
+/// ```
+#[derive(Clone)]
+pub struct TimedMovingMedian<T, const N: usize> {
+    values: [T; N],
+    // The tick at which the value in the same slot was taken, compared with `now` on every query
+    timestamps: [u64; N],
+    index: usize,
+    count: usize,
+}
+
+impl<T: Sample, const N: usize> TimedMovingMedian<T, N> {
+    /// Create a new timed moving median filter with a fixed-size buffer of size N.
+    pub fn new() -> Self {
+        Self {
+            values: [T::zero(); N],
+            timestamps: [0; N],
+            index: 0,
+            count: 0,
+        }
+    }
+
+    /// Add a new measurement taken at `timestamp` to the buffer.
+    /// If the buffer is full, the oldest measurement will be replaced, whatever its age.
+    /// Returns the replaced oldest measurement and its timestamp, or `None` while the buffer is still filling up.
+    pub fn add_value(&mut self, value: T, timestamp: u64) -> Option<(T, u64)> {
+        let evicted = if self.count == N {
+            Some((self.values[self.index], self.timestamps[self.index]))
+        } else {
+            self.count += 1;
+            None
+        };
+        self.values[self.index] = value;
+        self.timestamps[self.index] = timestamp;
+        // Move to the next index, wrapping around if necessary
+        self.index = (self.index + 1) % N;
+        evicted
+    }
+
+    /// Remove all values from the buffer, so the filter starts over as if it was new.
+    pub fn clear(&mut self) {
+        self.index = 0;
+        self.count = 0;
+    }
+
+    /// Number of values in the buffer, at most N, whatever their age.
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    /// Returns `true` if no values have been added.
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Size N of the buffer, which is the number of values the filter holds once it is full.
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
+    /// Number of values in the buffer that are at most `max_age` ticks old at `now`. NaN values are included.
+    pub fn fresh_len(&self, now: u64, max_age: u64) -> usize {
+        (0..self.count)
+            .filter(|&i| Self::is_fresh(self.timestamps[i], now, max_age))
+            .count()
+    }
+
+    /// Calculate the median of the values in the buffer that are at most `max_age` ticks old at `now`.
+    /// If the number of those values is even, the median is the average of the two middle values,
+    /// rounded towards negative infinity for integers (see [`Sample::midpoint`]).
+    /// Returns `None` if no values other than NaN are recent enough.
+    pub fn median_at(&self, now: u64, max_age: u64) -> Option<T> {
+        let mut sorted = [T::zero(); N];
+        let mut len = 0;
+        for i in 0..self.count {
+            let value = self.values[i];
+            if !value.is_nan() && Self::is_fresh(self.timestamps[i], now, max_age) {
+                sorted[len] = value;
+                len += 1;
+            }
+        }
+        let sorted = &mut sorted[..len];
+        sorted.sort_unstable_by(|a, b| a.partial_cmp(b).unwrap());
+        middle(sorted, T::midpoint)
+    }
+
+    fn is_fresh(timestamp: u64, now: u64, max_age: u64) -> bool {
+        now.saturating_sub(timestamp) <= max_age
+    }
+}
+
+impl<T: Sample, const N: usize> Default for TimedMovingMedian<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn all_stale_values_have_no_median() {
+        let mut filter = TimedMovingMedian::<i32, 4>::new();
+        assert_eq!(filter.median_at(0, 10), None);
+        filter.add_value(5, 100);
+        filter.add_value(7, 110);
+        assert_eq!(filter.median_at(121, 10), None);
+        assert_eq!(filter.fresh_len(121, 10), 0);
+        // The values are still there for a longer maximum age
+        assert_eq!(filter.median_at(121, 21), Some(6));
+        assert_eq!(filter.len(), 2);
+    }
+
+    #[test]
+    fn median_uses_only_fresh_values_of_a_mixed_window() {
+        let mut filter = TimedMovingMedian::<f64, 5>::new();
+        filter.add_value(100.0, 0);
+        filter.add_value(1.0, 500);
+        filter.add_value(3.0, 900);
+        filter.add_value(f64::NAN, 950);
+        filter.add_value(2.0, 1_000);
+        assert_eq!(filter.median_at(1_000, 500), Some(2.0));
+        assert_eq!(filter.fresh_len(1_000, 500), 4);
+        assert_eq!(filter.median_at(1_000, 100), Some(2.5));
+        assert_eq!(filter.median_at(1_000, 1_000), Some(2.5));
+        // A timestamp after the query time counts as fresh
+        assert_eq!(filter.median_at(0, 0), Some(2.5));
+    }
+
+    #[test]
+    fn capacity_evicts_oldest_whatever_its_age() {
+        let mut filter = TimedMovingMedian::<u8, 3>::new();
+        for (i, value) in [9, 1, 2, 3].into_iter().enumerate() {
+            filter.add_value(value, i as u64);
+        }
+        assert_eq!(filter.add_value(4, 10), Some((1, 1)));
+        assert_eq!(filter.median_at(10, u64::MAX), Some(3));
+        filter.clear();
+        assert!(filter.is_empty());
+        assert_eq!(filter.median_at(10, u64::MAX), None);
+    }
+}
@@ -0,0 +1,211 @@
+//! A moving median filter where every value carries a weight.
+
+use crate::{insertion_sort_by, weighted_median_of, Sample};
+
+/// A no-std moving weighted median filter with a fixed-size buffer of size N.
+///
+/// Every value is added together with a weight, such as the confidence in a measurement. The
+/// weighted median is the value at which the cumulative weight of the values, taken in ascending
+/// order, first reaches half of the total weight. If the cumulative weight is exactly half of the
+/// total, the median is the average of the two values on either side, like the unweighted median
+/// of an even number of values. With equal weights the weighted median is the plain median.
+///
+/// Weights must not be negative. Values with a weight of zero stay in the window but do not
+/// affect the median. NaN values are left out of the median.
+///
+/// ```
+/// use moving_median::WeightedMovingMedian;
+///
+/// let mut filter = WeightedMovingMedian::<f32, f32, 3>::new();
+/// filter.add_weighted(10.0, 1.0);
+/// filter.add_weighted(20.0, 1.0);
+/// filter.add_weighted(30.0, 5.0);
+///
+/// assert_eq!(filter.median(), 30.0);
+/// ```
+#[derive(Clone)]
+pub struct WeightedMovingMedian<T, W, const N: usize> {
+    // The values and their weights in parallel rings, sorted together on every query
+    values: [T; N],
+    weights: [W; N],
+    index: usize,
+    count: usize,
+}
+
+impl<T: Sample, W: Sample, const N: usize> WeightedMovingMedian<T, W, N> {
+    /// Create a new weighted moving median filter with a fixed-size buffer of size N.
+    pub fn new() -> Self {
+        Self {
+            values: [T::zero(); N],
+            weights: [W::zero(); N],
+            index: 0,
+            count: 0,
+        }
+    }
+
+    /// Add a new measurement with its weight to the buffer.
+    /// If the buffer is full, the oldest measurement will be replaced.
+    /// Returns the replaced oldest measurement and its weight, or `None` while the buffer is still filling up.
+    pub fn add_weighted(&mut self, value: T, weight: W) -> Option<(T, W)> {
+        let evicted = if self.count == N {
+            Some((self.values[self.index], self.weights[self.index]))
+        } else {
+            self.count += 1;
+            None
+        };
+        self.values[self.index] = value;
+        self.weights[self.index] = weight;
+        // Move to the next index, wrapping around if necessary
+        self.index = (self.index + 1) % N;
+        evicted
+    }
+
+    /// Remove all values from the buffer, so the filter starts over as if it was new.
+    pub fn clear(&mut self) {
+        self.index = 0;
+        self.count = 0;
+    }
+
+    /// Number of values in the buffer, at most N.
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    /// Returns `true` if no values have been added.
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Returns `true` once N values have been added and the buffer has warmed up.
+    pub fn is_full(&self) -> bool {
+        self.count == N
+    }
+
+    /// Size N of the buffer, which is the number of values the filter holds once it is full.
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
+    /// Calculate the weighted median of the values in the buffer.
+    /// If no values with a weight above zero have been added, zero is returned.
+    pub fn median(&self) -> T {
+        self.try_median().unwrap_or(T::zero())
+    }
+
+    /// Calculate the weighted median of the values in the buffer, like [`median`](Self::median).
+    /// Returns `None` if the buffer holds no values with a weight above zero.
+    pub fn try_median(&self) -> Option<T> {
+        // Collect the values that take part, sorted by value
+        let mut pairs = [(T::zero(), W::zero().accumulate()); N];
+        let mut len = 0;
+        for i in 0..self.count {
+            let (value, weight) = (self.values[i], self.weights[i]);
+            if !value.is_nan() && weight > W::zero() {
+                pairs[len] = (value, weight.accumulate());
+                len += 1;
+            }
+        }
+        let pairs = &mut pairs[..len];
+        insertion_sort_by(pairs, |a, b| a.0 < b.0);
+
+        let total = pairs
+            .iter()
+            .fold(W::zero().accumulate(), |sum, pair| sum + pair.1);
+        weighted_median_of(pairs, total)
+    }
+}
+
+impl<T: Sample, W: Sample, const N: usize> Default for WeightedMovingMedian<T, W, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::XorShift;
+    use crate::MovingMedian;
+
+    #[test]
+    fn median_is_none_when_no_values_added() {
+        let filter = WeightedMovingMedian::<f64, f64, 3>::new();
+        assert_eq!(filter.try_median(), None);
+        assert_eq!(filter.median(), 0.0);
+    }
+
+    #[test]
+    fn median_matches_plain_median_for_equal_weights() {
+        let mut rng = XorShift(0x2545_f491_4f6c_dd1d);
+        let mut weighted = WeightedMovingMedian::<i32, u8, 6>::new();
+        let mut plain = MovingMedian::<i32, 6>::new();
+        for _ in 0..1_000 {
+            let value = (rng.next() % 100) as i32 - 50;
+            weighted.add_weighted(value, 3);
+            plain.add_value(value);
+            assert_eq!(weighted.try_median(), plain.try_median());
+        }
+    }
+
+    #[test]
+    fn heavy_value_dominates_median() {
+        let mut filter = WeightedMovingMedian::<f64, f64, 5>::new();
+        filter.add_weighted(1.0, 1.0);
+        filter.add_weighted(2.0, 1.0);
+        filter.add_weighted(3.0, 1.0);
+        filter.add_weighted(4.0, 1.0);
+        filter.add_weighted(100.0, 10.0);
+        assert_eq!(filter.median(), 100.0);
+    }
+
+    #[test]
+    fn heavy_outlier_shifts_median() {
+        let mut weighted = WeightedMovingMedian::<f64, f64, 5>::new();
+        let mut plain = MovingMedian::<f64, 5>::new();
+        for value in [1.0, 2.0, 3.0, 4.0, 5.0] {
+            weighted.add_weighted(value, 1.0);
+            plain.add_value(value);
+        }
+        assert_eq!(weighted.median(), plain.median());
+        // Replacing the oldest value by an outlier with weight 3 out of 7 moves the median up by one
+        weighted.add_weighted(90.0, 3.0);
+        plain.add_value(90.0);
+        assert_eq!(plain.median(), 4.0);
+        assert_eq!(weighted.median(), 5.0);
+    }
+
+    #[test]
+    fn median_averages_values_straddling_half_of_total_weight() {
+        let mut filter = WeightedMovingMedian::<f64, u32, 3>::new();
+        filter.add_weighted(10.0, 2);
+        filter.add_weighted(20.0, 1);
+        filter.add_weighted(30.0, 1);
+        assert_eq!(filter.median(), 15.0);
+    }
+
+    #[test]
+    fn zero_weight_values_are_ignored() {
+        let mut filter = WeightedMovingMedian::<f64, f64, 4>::new();
+        filter.add_weighted(1.0, 1.0);
+        filter.add_weighted(1000.0, 0.0);
+        filter.add_weighted(3.0, 1.0);
+        filter.add_weighted(2.0, 1.0);
+        assert_eq!(filter.median(), 2.0);
+        assert_eq!(filter.len(), 4);
+
+        let mut filter = WeightedMovingMedian::<f64, f64, 2>::new();
+        filter.add_weighted(1.0, 0.0);
+        assert_eq!(filter.try_median(), None);
+    }
+
+    #[test]
+    fn oldest_value_and_weight_are_evicted() {
+        let mut filter = WeightedMovingMedian::<i32, u16, 2>::new();
+        assert_eq!(filter.add_weighted(1, 100), None);
+        assert_eq!(filter.add_weighted(2, 1), None);
+        assert_eq!(filter.median(), 1);
+        assert_eq!(filter.add_weighted(3, 1), Some((1, 100)));
+        // Values 2 and 3 have the same weight now
+        assert_eq!(filter.median(), 2);
+    }
+}